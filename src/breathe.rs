@@ -0,0 +1,94 @@
+use crate::LEDArray;
+
+/// A slow pulse of the whole panel's brightness, for idle screens.
+///
+/// The brightness follows a triangle wave over `frame_count`, rising from
+/// `min` to `max` over the first half of each `period` frames and falling
+/// back over the second half.
+pub struct Breathe {
+    /// Frames per full breath.
+    pub period: u32,
+    pub min: u8,
+    pub max: u8,
+}
+
+impl Breathe {
+    pub fn new(period: u32, min: u8, max: u8) -> Self {
+        Breathe { period, min, max }
+    }
+
+    /// The brightness for frame `frame`.
+    pub fn level(&self, frame: u32) -> u8 {
+        let half = self.period / 2;
+        if half == 0 {
+            return self.max;
+        }
+
+        let phase = frame % self.period;
+        let t = if phase < half {
+            phase
+        } else {
+            self.period - phase
+        }
+        .min(half);
+
+        // wide enough for any period
+        let span = self.max as i64 - self.min as i64;
+        let offset = (span * t as i64 + span.signum() * half as i64 / 2) / half as i64;
+        (self.min as i64 + offset) as u8
+    }
+
+    /// Set the array's `brightness` for its current `frame_count`. Does
+    /// nothing while the array is paused.
+    pub fn apply<R0, R1, R2, Timer, SPI, Reg, OD>(
+        &self,
+        array: &mut LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>,
+    ) {
+        if array.is_paused() {
+            return;
+        }
+        array.brightness = self.level(array.frame_count);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_array;
+
+    #[test]
+    fn test_breathe_level() {
+        let breathe = Breathe::new(8, 15, 255);
+
+        let levels: heapless::Vec<u8, 9> = (0..9).map(|frame| breathe.level(frame)).collect();
+        assert_eq!(levels, [15, 75, 135, 195, 255, 195, 135, 75, 15]);
+
+        // an upside down breath runs the other way
+        let inverted = Breathe::new(4, 200, 100);
+        assert_eq!(inverted.level(0), 200);
+        assert_eq!(inverted.level(1), 150);
+        assert_eq!(inverted.level(2), 100);
+
+        // the longest breath doesn't overflow
+        let slow = Breathe::new(u32::MAX, 0, 255);
+        assert_eq!(slow.level(0), 0);
+        assert_eq!(slow.level(u32::MAX / 4), 127);
+        assert_eq!(slow.level(u32::MAX / 2), 255);
+        assert_eq!(slow.level(u32::MAX - 1), 0);
+    }
+
+    #[test]
+    fn test_breathe_apply() {
+        let mut array = mock_array();
+        let breathe = Breathe::new(8, 15, 255);
+
+        array.frame_count = 10;
+        breathe.apply(&mut array);
+        assert_eq!(array.brightness, 135);
+
+        array.pause();
+        array.frame_count = 12;
+        breathe.apply(&mut array);
+        assert_eq!(array.brightness, 135);
+    }
+}
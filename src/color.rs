@@ -0,0 +1,51 @@
+/// The order a color panel's channels are wired in, from the first plane
+/// shifted out to the last.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ColorOrder {
+    #[default]
+    Rgb,
+    Grb,
+    Bgr,
+}
+
+impl ColorOrder {
+    /// Arrange `(r, g, b)` into the order the panel's planes are wired in.
+    pub fn apply(self, (r, g, b): (u8, u8, u8)) -> [u8; 3] {
+        match self {
+            ColorOrder::Rgb => [r, g, b],
+            ColorOrder::Grb => [g, r, b],
+            ColorOrder::Bgr => [b, g, r],
+        }
+    }
+}
+
+/// The perceived brightness of an sRGB color, weighting the channels
+/// 0.299, 0.587 and 0.114 as in Rec. 601, in 8 bit fixed point.
+pub fn luma(r: u8, g: u8, b: u8) -> u8 {
+    ((77 * r as u32 + 150 * g as u32 + 29 * b as u32 + 128) >> 8) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_order() {
+        let red = (255, 0, 0);
+
+        assert_eq!(ColorOrder::Rgb.apply(red), [255, 0, 0]);
+        // on a GRB panel red has to go out where an RGB panel expects green
+        assert_eq!(ColorOrder::Grb.apply(red), [0, 255, 0]);
+        assert_eq!(ColorOrder::Bgr.apply(red), [0, 0, 255]);
+        assert_eq!(ColorOrder::Grb.apply((1, 2, 3)), [2, 1, 3]);
+    }
+
+    #[test]
+    fn test_luma() {
+        assert_eq!(luma(0, 0, 0), 0);
+        assert_eq!(luma(255, 255, 255), 255);
+        assert_eq!(luma(255, 0, 0), 77);
+        assert_eq!(luma(0, 255, 0), 149);
+        assert_eq!(luma(0, 0, 255), 29);
+    }
+}
@@ -0,0 +1,177 @@
+use core::fmt;
+
+use crate::fonts::{Font, Font5x7};
+use crate::LEDArray;
+
+/// Where the next glyph of text goes, for laying out text a piece at a time
+/// like a console.
+///
+/// `'\n'` starts a new line back at `left`, one glyph height plus a blank row
+/// further down. With `wrap` set, a glyph that would run off the right edge of
+/// the drawing area starts a new line first.
+pub struct Cursor<F = Font5x7> {
+    pub font: F,
+    pub x: i32,
+    pub y: i32,
+    /// The column each new line starts at.
+    pub left: i32,
+    pub brightness: u8,
+    pub wrap: bool,
+}
+
+impl<F: Font> Cursor<F> {
+    /// A wrapping cursor at `(x, y)`, with new lines starting at `x`.
+    pub fn new(font: F, x: i32, y: i32, brightness: u8) -> Self {
+        Cursor {
+            font,
+            x,
+            y,
+            left: x,
+            brightness,
+            wrap: true,
+        }
+    }
+
+    /// Move to the start of the next line.
+    pub fn newline(&mut self) {
+        self.x = self.left;
+        self.y = self.y.saturating_add(self.font.height() as i32 + 1);
+    }
+
+    /// Where to draw `c` in a drawing area `width` columns wide, moving past
+    /// it. Returns `None` for `'\n'`, which only moves the cursor.
+    pub fn advance(&mut self, c: char, width: usize) -> Option<(i32, i32)> {
+        if c == '\n' {
+            self.newline();
+            return None;
+        }
+
+        // a glyph too wide for the area would wrap forever, so it only wraps
+        // when it isn't already at the start of a line
+        let right = i64::from(self.x) + self.font.width() as i64;
+        if self.wrap && self.x > self.left && right > width as i64 {
+            self.newline();
+        }
+
+        let at = (self.x, self.y);
+        self.x = self.x.saturating_add(self.font.advance() as i32);
+        Some(at)
+    }
+}
+
+impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    /// Draw `text` at `cursor`, leaving the cursor just after it.
+    pub fn draw_text<F: Font>(&mut self, cursor: &mut Cursor<F>, text: &str) {
+        let width = self.transform.size().0;
+        for c in text.chars() {
+            if let Some((x, y)) = cursor.advance(c, width) {
+                self.draw_char_with(&cursor.font, x, y, c, cursor.brightness);
+            }
+        }
+    }
+
+    /// Borrow the array and `cursor` together as a `core::fmt::Write`, so
+    /// text can be drawn with `write!`.
+    pub fn writer<'a, F: Font>(
+        &'a mut self,
+        cursor: &'a mut Cursor<F>,
+    ) -> CursorWriter<'a, F, R0, R1, R2, Timer, SPI, Reg, OD> {
+        CursorWriter {
+            array: self,
+            cursor,
+        }
+    }
+}
+
+/// Draws whatever is written to it at a [`Cursor`]. Made by
+/// [`LEDArray::writer`].
+pub struct CursorWriter<'a, F, R0, R1, R2, Timer, SPI, Reg, OD> {
+    array: &'a mut LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>,
+    cursor: &'a mut Cursor<F>,
+}
+
+impl<'a, F, R0, R1, R2, Timer, SPI, Reg, OD> fmt::Write
+    for CursorWriter<'a, F, R0, R1, R2, Timer, SPI, Reg, OD>
+where
+    F: Font,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.array.draw_text(self.cursor, s);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fonts::Font3x5;
+    use crate::tests::mock_array;
+    use crate::Rotation;
+    use core::fmt::Write;
+
+    #[test]
+    fn test_cursor_newline() {
+        let mut array = mock_array();
+        let mut cursor = Cursor::new(Font3x5, 1, 0, 5);
+
+        array.draw_text(&mut cursor, "-\n1");
+        assert_eq!(array.array[2][1..4], [5, 5, 5]);
+        // the top of the 1 starts back at the left, one text row down
+        assert_eq!(array.array[6][1..4], [0, 5, 0]);
+        assert_eq!(array.array[7][1..4], [5, 5, 0]);
+        assert_eq!((cursor.x, cursor.y), (5, 6));
+    }
+
+    #[test]
+    fn test_cursor_wraps() {
+        let mut cursor = Cursor::new(Font3x5, 0, 0, 5);
+
+        // four glyphs fit across 16 columns, the fifth starts a new line
+        for i in 0..4 {
+            assert_eq!(cursor.advance('A', 16), Some((i * 4, 0)));
+        }
+        assert_eq!(cursor.advance('A', 16), Some((0, 6)));
+
+        cursor.wrap = false;
+        cursor.x = 16;
+        assert_eq!(cursor.advance('A', 16), Some((16, 6)));
+    }
+
+    #[test]
+    fn test_cursor_wraps_transformed() {
+        let mut array = mock_array();
+        array.transform.rotation = Rotation::Deg90;
+        let mut cursor = Cursor::new(Font3x5, 0, 0, 5);
+
+        // turned a quarter the drawing area is only 8 columns wide
+        array.draw_text(&mut cursor, "---");
+        assert_eq!((cursor.x, cursor.y), (4, 6));
+        // the third dash is on the second line, 8 drawing rows down
+        assert!(array.array[0..3].iter().all(|row| row[16 - 1 - 8] == 5));
+    }
+
+    #[test]
+    fn test_cursor_at_the_limits() {
+        let mut cursor = Cursor::new(Font3x5, 0, i32::MAX - 2, 5);
+        assert_eq!(cursor.advance('\n', 16), None);
+        assert_eq!(cursor.y, i32::MAX);
+
+        cursor.x = i32::MAX - 1;
+        assert_eq!(cursor.advance('A', 16), Some((0, i32::MAX)));
+        cursor.wrap = false;
+        cursor.x = i32::MAX - 1;
+        assert_eq!(cursor.advance('A', 16), Some((i32::MAX - 1, i32::MAX)));
+        assert_eq!(cursor.x, i32::MAX);
+    }
+
+    #[test]
+    fn test_cursor_writer() {
+        let mut array = mock_array();
+        let mut cursor = Cursor::new(Font3x5, 0, 0, 5);
+
+        write!(array.writer(&mut cursor), "{}", 11).unwrap();
+        assert_eq!(cursor.x, 8);
+        // the stems of both 1s
+        assert_eq!(array.array[2][..8], [0, 5, 0, 0, 0, 5, 0, 0]);
+    }
+}
@@ -0,0 +1,118 @@
+use crate::hal;
+use crate::{LEDArray, LEDError, NUM_COLS, SPI_BYTES};
+use hal::digital::v2::OutputPin;
+use hal::spi::FullDuplex;
+
+impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    /// Look for dead columns on boards that wire the last shift register's
+    /// serial output back to MISO, returning the columns that failed.
+    ///
+    /// Two checkerboard patterns are shifted through the chain and compared
+    /// with what comes out the other end, so every bit is seen both high and
+    /// low. Dead rows can't be found this way, as the row drivers aren't part
+    /// of the chain. The panel is blanked and nothing is latched, so it shows
+    /// nothing until the next scan.
+    pub fn diagnose<PinError>(
+        &mut self,
+    ) -> Result<heapless::Vec<usize, NUM_COLS>, LEDError<PinError, SPI::Error>>
+    where
+        SPI: FullDuplex<u8>,
+        OD: OutputPin<Error = PinError>,
+    {
+        self.ensure_blanked().map_err(LEDError::PinError)?;
+        // the patterns are left in the registers
+        self.forget_sent();
+
+        // the last pattern only pushes the one before it out
+        let patterns = [[0x55; SPI_BYTES], [0xaa; SPI_BYTES], [0x00; SPI_BYTES]];
+        let mut bad = [0u8; SPI_BYTES];
+        let mut previous: Option<[u8; SPI_BYTES]> = None;
+        for pattern in patterns.iter() {
+            let mut back = [0u8; SPI_BYTES];
+            for (&out, read) in pattern.iter().zip(back.iter_mut()) {
+                let spi = &mut self.spi;
+                retry(self.max_spi_spins, || spi.send(out))?;
+                *read = retry(self.max_spi_spins, || spi.read())?;
+            }
+
+            if let Some(expected) = previous {
+                for (bad, (expected, got)) in bad.iter_mut().zip(expected.iter().zip(back.iter())) {
+                    *bad |= expected ^ got;
+                }
+            }
+            previous = Some(*pattern);
+        }
+
+        // same layout as `pack_layer`
+        let mut failed = heapless::Vec::new();
+        for col in 0..NUM_COLS {
+            if (bad[SPI_BYTES - 1 - col / 8] >> (col % 8)) & 1 == 1 {
+                // there are only NUM_COLS columns, so this always fits
+                let _ = failed.push(col);
+            }
+        }
+        Ok(failed)
+    }
+}
+
+/// Call `f` until it stops returning `WouldBlock`, at most `max_spins` extra
+/// times.
+fn retry<T, P, S, F>(max_spins: Option<u32>, mut f: F) -> Result<T, LEDError<P, S>>
+where
+    F: FnMut() -> nb::Result<T, S>,
+{
+    let mut spins = 0u32;
+    loop {
+        match f() {
+            Ok(value) => return Ok(value),
+            Err(nb::Error::WouldBlock) => {
+                spins = spins.saturating_add(1);
+                if max_spins.is_some_and(|max| spins > max) {
+                    return Err(LEDError::SpiTimeout);
+                }
+            }
+            Err(nb::Error::Other(error)) => return Err(LEDError::SPIError(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::mock_array;
+    use crate::LEDError;
+
+    #[test]
+    fn test_diagnose() {
+        let mut array = mock_array();
+        array.spi.loopback = true;
+        assert_eq!(array.diagnose().unwrap(), []);
+        assert!(array.output_disable.state);
+
+        let mut array = mock_array();
+        array.spi.loopback = true;
+        // the second byte out holds columns 0 to 7
+        array.spi.stuck_low = [0x00, 0x04];
+        assert_eq!(array.diagnose().unwrap(), [2]);
+    }
+
+    #[test]
+    fn test_diagnose_spi_timeout() {
+        let mut array = mock_array();
+        array.spi.loopback = true;
+        array.max_spi_spins = Some(3);
+
+        // a slow bus is waited out
+        array.spi.busy = 3;
+        assert_eq!(array.diagnose().unwrap(), []);
+
+        // a stuck one gives up
+        array.spi.busy = 4;
+        assert!(matches!(array.diagnose(), Err(LEDError::SpiTimeout)));
+    }
+
+    #[test]
+    fn test_diagnose_without_readback() {
+        let mut array = mock_array();
+        assert!(array.diagnose().is_err());
+    }
+}
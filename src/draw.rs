@@ -0,0 +1,838 @@
+use core::convert::TryFrom;
+
+use crate::color::luma;
+use crate::math::{atan2_u8, sin_u8};
+use crate::{scale, Frame, LEDArray, LAYER_BITS, NUM_COLS, NUM_ROWS};
+
+/// An 8 bit grayscale image that can be copied onto the panel in one go, such
+/// as an embedded-graphics framebuffer wrapped to read its pixels.
+pub trait GrayImage {
+    /// The luma at column `x`, row `y`, or `None` off the edge of the image.
+    fn luma(&self, x: usize, y: usize) -> Option<u8>;
+}
+
+/// Which way a gradient runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// From the left column to the right column.
+    Horizontal,
+    /// From the top row to the bottom row.
+    Vertical,
+}
+
+/// The `(x, y)` drawing coordinates inside both the `width` by `height`
+/// rectangle at `(x, y)` and a drawing area of `area`, however large or far
+/// off the rectangle is.
+pub(crate) fn rect_cells(
+    area: (usize, usize),
+    x: i32,
+    y: i32,
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = (i32, i32)> {
+    let span = |start: i32, size: usize, limit: usize| {
+        let end = (start as i64).saturating_add(size.min(i64::MAX as usize) as i64);
+        let end = end.min(limit as i64).max(0) as i32;
+        start.max(0).min(end)..end
+    };
+    let columns = span(x, width, area.0);
+    span(y, height, area.1).flat_map(move |y| columns.clone().map(move |x| (x, y)))
+}
+
+/// `rect_cells`, along with how far each cell is from the rectangle's top left
+/// corner, for drawing images and glyphs.
+pub(crate) fn rect_offsets(
+    area: (usize, usize),
+    x: i32,
+    y: i32,
+    width: usize,
+    height: usize,
+) -> impl Iterator<Item = ((i32, i32), (usize, usize))> {
+    let offset = |from: i32, to: i32| (to as i64 - from as i64) as usize;
+    rect_cells(area, x, y, width, height)
+        .map(move |(px, py)| ((px, py), (offset(x, px), offset(y, py))))
+}
+
+impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    /// Turn every pixel off.
+    pub fn clear(&mut self) {
+        self.touch();
+        self.array = [[0; NUM_COLS]; NUM_ROWS];
+    }
+
+    /// Limit drawing to the `width` by `height` rectangle with its top left
+    /// corner at `(x, y)`, so a widget can't spill into its neighbours.
+    /// `set_pixel` and everything drawn through it or `draw_iter` skip pixels
+    /// outside it. Whole panel operations like `clear` and `load_frame` don't.
+    pub fn set_clip(&mut self, x: i32, y: i32, width: usize, height: usize) {
+        let extend =
+            |start: i32, size: usize| start.saturating_add(i32::try_from(size).unwrap_or(i32::MAX));
+        self.clip = Some((x, y, extend(x, width), extend(y, height)));
+    }
+
+    /// Let drawing reach the whole panel again.
+    pub fn clear_clip(&mut self) {
+        self.clip = None;
+    }
+
+    /// Whether column `x`, row `y` is inside the clip rectangle.
+    fn in_clip(&self, x: i32, y: i32) -> bool {
+        match self.clip {
+            Some((left, top, right, bottom)) => x >= left && x < right && y >= top && y < bottom,
+            None => true,
+        }
+    }
+
+    /// Set the brightness at `(row, col)`, through `transform`. Out of bounds
+    /// coordinates, and ones outside the clip rectangle, are ignored.
+    pub fn set_pixel(&mut self, row: usize, col: usize, brightness: u8) {
+        let (x, y) = match (i32::try_from(col), i32::try_from(row)) {
+            (Ok(x), Ok(y)) => (x, y),
+            _ => return,
+        };
+        self.draw_iter(Some(((x, y), brightness)));
+    }
+
+    /// Set the brightness at `(row, col)` from an 8 bit per channel color,
+    /// using its luminance rather than a plain average so e.g. green looks
+    /// as bright as it would on a color panel.
+    pub fn set_pixel_rgb(&mut self, row: usize, col: usize, r: u8, g: u8, b: u8) {
+        self.set_pixel(row, col, luma(r, g, b) >> 4);
+    }
+
+    /// The brightness at `(row, col)`, through `transform`, or `None` if it
+    /// is off the panel.
+    pub fn get_pixel(&self, row: usize, col: usize) -> Option<u8> {
+        let (row, col) = self
+            .transform
+            .apply(i32::try_from(col).ok()?, i32::try_from(row).ok()?)?;
+        self.array.get(row).and_then(|r| r.get(col)).copied()
+    }
+
+    /// Set the brightness of the LED `index` places along the panel in row
+    /// major order, as if it were one long strip. Indexes past the last LED
+    /// are ignored.
+    pub fn set_led(&mut self, index: usize, brightness: u8) {
+        self.set_pixel(index / NUM_COLS, index % NUM_COLS, brightness);
+    }
+
+    /// Draw a vertical bar in `column`, lighting `level` cells up from the
+    /// bottom of the drawing area and turning off the rest of the column, so a
+    /// meter can be redrawn in place. Levels past the top fill the whole
+    /// column.
+    pub fn draw_bar(&mut self, column: usize, level: usize, brightness: u8) {
+        let rows = self.transform.size().1;
+        for height in 0..rows {
+            let value = if height < level { brightness } else { 0 };
+            self.set_pixel(rows - 1 - height, column, value);
+        }
+    }
+
+    /// Draw a bar for each entry in `levels`, starting from the left column.
+    pub fn draw_bars(&mut self, levels: &[u8], brightness: u8) {
+        for (column, &level) in levels.iter().enumerate() {
+            self.draw_bar(column, level as usize, brightness);
+        }
+    }
+
+    /// Apply a batch of `((row, col), brightness)` updates, skipping any that
+    /// are out of bounds.
+    pub fn set_pixels<I>(&mut self, pixels: I)
+    where
+        I: IntoIterator<Item = ((usize, usize), u8)>,
+    {
+        for ((row, col), brightness) in pixels {
+            self.set_pixel(row, col, brightness);
+        }
+    }
+
+    /// Write each `((x, y), brightness)` pixel into the array, where `x` is the
+    /// column and `y` is the row, mapped through `transform`. Pixels outside
+    /// the panel or the clip rectangle are skipped.
+    ///
+    /// Like every drawing method this only changes `array`. Nothing reaches the
+    /// panel until the next `scan` or `step`, so any number of draws can be
+    /// batched into one frame.
+    pub fn draw_iter<I>(&mut self, pixels: I)
+    where
+        I: IntoIterator<Item = ((i32, i32), u8)>,
+    {
+        self.touch();
+        for ((x, y), brightness) in pixels {
+            if !self.in_clip(x, y) {
+                continue;
+            }
+            if let Some((row, col)) = self.transform.apply(x, y) {
+                if let Some(cell) = self.array.get_mut(row).and_then(|r| r.get_mut(col)) {
+                    *cell = brightness;
+                }
+            }
+        }
+    }
+
+    /// Fill a `width` by `height` rectangle with its top left corner at
+    /// `(x, y)`, clipped to the panel.
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: usize, height: usize, brightness: u8) {
+        let pixels = rect_cells(self.transform.size(), x, y, width, height);
+        self.draw_iter(pixels.map(|at| (at, brightness)));
+    }
+
+    /// Multiply the brightness of every pixel in a `width` by `height`
+    /// rectangle by `factor / 255`, for dimming or highlighting a region. The
+    /// rectangle goes through `transform` and is clipped like `fill_rect`'s.
+    pub fn scale_rect(&mut self, x: i32, y: i32, width: usize, height: usize, factor: u8) {
+        self.touch();
+        for (x, y) in rect_cells(self.transform.size(), x, y, width, height) {
+            if !self.in_clip(x, y) {
+                continue;
+            }
+            if let Some((row, col)) = self.transform.apply(x, y) {
+                if let Some(cell) = self.array.get_mut(row).and_then(|r| r.get_mut(col)) {
+                    *cell = scale(*cell, factor);
+                }
+            }
+        }
+    }
+
+    /// Replace the whole array with the top left corner of `image`, keeping the
+    /// top 4 bits of each pixel's luma as its brightness. Cells the image
+    /// doesn't cover are turned off.
+    pub fn copy_from_image<D>(&mut self, image: &D)
+    where
+        D: GrayImage,
+    {
+        self.touch();
+        for (y, row) in self.array.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                *cell = image.luma(x, y).map_or(0, |luma| luma >> 4);
+            }
+        }
+    }
+
+    /// Blend the newly drawn array with the result of the previous call, to
+    /// soften pixels that flip rapidly between frames.
+    ///
+    /// Each pixel moves `factor / 255` of the way from its previous smoothed
+    /// value toward its new one, so 255 turns smoothing off and lower values
+    /// smooth more. Call it once per new frame, after drawing.
+    pub fn apply_smoothing(&mut self, factor: u8) {
+        self.touch();
+        let factor = factor as u16;
+        for (row, previous) in self.array.iter_mut().zip(self.smoothed.iter_mut()) {
+            for (cell, old) in row.iter_mut().zip(previous.iter_mut()) {
+                let blended = (*old as u16 * (255 - factor) + *cell as u16 * factor + 127) / 255;
+                *cell = blended as u8;
+                *old = *cell;
+            }
+        }
+    }
+
+    /// Draw `source` over the whole array, shifted right by `offset` 256ths of
+    /// a column (left when negative).
+    ///
+    /// When the offset isn't a whole number of columns, each pixel's
+    /// brightness is split between the two columns it straddles, so a scroll
+    /// can move smoothly by less than a column per frame.
+    pub fn scroll_fractional(&mut self, source: &Frame, offset: i32) {
+        let whole = offset >> 8;
+        let fraction = (offset & 0xff) as u16;
+
+        let column = |row: &[u8; NUM_COLS], col: i32| -> u16 {
+            if col < 0 {
+                return 0;
+            }
+            row.get(col as usize).copied().unwrap_or(0) as u16
+        };
+
+        self.touch();
+        for (out, row) in self.array.iter_mut().zip(source.0.iter()) {
+            for (col, cell) in out.iter_mut().enumerate() {
+                let from = col as i32 - whole;
+                let blended =
+                    column(row, from) * (256 - fraction) + column(row, from - 1) * fraction;
+                *cell = ((blended + 128) >> 8) as u8;
+            }
+        }
+    }
+
+    /// Copy a packed 4 bit grayscale image with its top left corner at `(x, y)`.
+    ///
+    /// `data` uses the same layout as embedded-graphics' `ImageRaw<Gray4>`: two
+    /// pixels per byte with the high nibble first, and each row padded to a
+    /// whole byte. Any part of the image that falls off the panel is clipped.
+    pub fn blit(&mut self, x: i32, y: i32, width: usize, data: &[u8]) {
+        if width == 0 {
+            return;
+        }
+        let stride = width.div_ceil(2);
+        let height = data.len().div_ceil(stride);
+
+        let area = self.transform.size();
+        let pixels = rect_offsets(area, x, y, width, height).map(|(at, (dx, dy))| {
+            let byte = data.get(dy * stride + dx / 2).copied().unwrap_or(0);
+            let value = if dx % 2 == 0 { byte >> 4 } else { byte & 0xf };
+            (at, value)
+        });
+
+        self.draw_iter(pixels);
+    }
+
+    /// `blit`, drawing only the pixels whose entry in `alpha` is `true`, so
+    /// sprites can have see through parts without giving up a brightness
+    /// level to mark them. `alpha` has one entry per image pixel, row after
+    /// row with no padding; pixels past its end are left out.
+    pub fn blit_masked(&mut self, x: i32, y: i32, width: usize, data: &[u8], alpha: &[bool]) {
+        if width == 0 {
+            return;
+        }
+        let stride = width.div_ceil(2);
+        let height = data.len().div_ceil(stride);
+
+        let area = self.transform.size();
+        let pixels = rect_offsets(area, x, y, width, height).filter_map(|(at, (dx, dy))| {
+            if !alpha.get(dy * width + dx).copied().unwrap_or(false) {
+                return None;
+            }
+            let byte = data.get(dy * stride + dx / 2).copied().unwrap_or(0);
+            let value = if dx % 2 == 0 { byte >> 4 } else { byte & 0xf };
+            Some((at, value))
+        });
+
+        self.draw_iter(pixels);
+    }
+
+    /// Stretch the brightness in `array` linearly so its dimmest pixel is off
+    /// and its brightest is the top level, for making the most of data like
+    /// a sensor heatmap that only spans part of the range. A panel where
+    /// every pixel is the same is left alone.
+    pub fn auto_contrast(&mut self) {
+        let top = (1u32 << LAYER_BITS) - 1;
+        let cells = self.array.iter().flatten();
+        let min = cells.clone().copied().min().unwrap_or(0) as u32;
+        let max = cells.copied().max().unwrap_or(0) as u32;
+        if min == max {
+            return;
+        }
+
+        self.touch();
+        let span = max - min;
+        for cell in self.array.iter_mut().flatten() {
+            *cell = (((*cell as u32 - min) * top + span / 2) / span) as u8;
+        }
+    }
+
+    /// Fill the panel with a plasma, an interference pattern of sine waves
+    /// that drifts as `frame_count` goes up. `scale` sets how tightly the
+    /// waves are packed, in 256ths of a turn per pixel. The same inputs always
+    /// draw the same frame.
+    pub fn plasma(&mut self, frame_count: u32, scale: u8) {
+        self.touch();
+        let t = frame_count as u8;
+        for (y, row) in self.array.iter_mut().enumerate() {
+            for (x, cell) in row.iter_mut().enumerate() {
+                let (x, y) = (x as u8, y as u8);
+                let sum = sin_u8(x.wrapping_mul(scale).wrapping_add(t)) as u16
+                    + sin_u8(y.wrapping_mul(scale).wrapping_sub(t / 2)) as u16
+                    + sin_u8(
+                        (x + y)
+                            .wrapping_mul(scale / 2)
+                            .wrapping_add(t.wrapping_mul(3)),
+                    ) as u16;
+                // average the three waves, then keep the top bits as a level
+                *cell = ((sum / 3) >> (8 - LAYER_BITS)) as u8;
+            }
+        }
+    }
+
+    /// Light the cells within a sweep of `percent` of a full turn around the
+    /// centre of the drawing area, clockwise from straight up, as a loading
+    /// indicator. Cells outside the sweep are left alone, and `percent` above
+    /// 100 is treated as 100.
+    pub fn draw_radial_progress(&mut self, percent: u8, brightness: u8) {
+        let limit = percent.min(100) as u32 * 256;
+        let (width, height) = self.transform.size();
+        let (width, height) = (width as i32, height as i32);
+        let pixels = (0..height).flat_map(|y| {
+            (0..width).filter_map(move |x| {
+                // doubled, so the centre between the middle cells is whole
+                let dx = 2 * x - (width - 1);
+                let dy = 2 * y - (height - 1);
+                // up is the start of the sweep and right is a quarter turn
+                let angle = atan2_u8(dx, -dy) as u32;
+                (angle * 100 < limit).then_some(((x, y), brightness))
+            })
+        });
+        self.draw_iter(pixels);
+    }
+
+    /// Fill the whole panel with a linear ramp from `start` at the first
+    /// column or row to `end` at the last, rounding to the nearest level.
+    pub fn gradient_fill(&mut self, start: u8, end: u8, direction: Direction) {
+        let steps = match direction {
+            Direction::Horizontal => NUM_COLS - 1,
+            Direction::Vertical => NUM_ROWS - 1,
+        } as i32;
+        let level = |i: usize| {
+            let span = (end as i32 - start as i32) * i as i32;
+            // round half away from zero so up and down ramps are mirror images
+            let offset = (span + span.signum() * steps / 2) / steps;
+            (start as i32 + offset) as u8
+        };
+
+        self.touch();
+        for (r, row) in self.array.iter_mut().enumerate() {
+            for (c, cell) in row.iter_mut().enumerate() {
+                *cell = match direction {
+                    Direction::Horizontal => level(c),
+                    Direction::Vertical => level(r),
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_array;
+    use crate::{Rotation, Transform};
+
+    #[test]
+    fn test_draw_iter_clips() {
+        let mut array = mock_array();
+
+        array.draw_iter([
+            ((-1, 0), 9),
+            ((0, 0), 1),
+            ((15, 7), 2),
+            ((16, 0), 9),
+            ((0, 8), 9),
+        ]);
+
+        assert_eq!(array.array[0][0], 1);
+        assert_eq!(array.array[7][15], 2);
+        let lit = array.array.iter().flatten().filter(|&&b| b != 0).count();
+        assert_eq!(lit, 2);
+    }
+
+    #[test]
+    fn test_blit_oversized_image() {
+        let mut array = mock_array();
+
+        // 20x10 image, every pixel 0xa, placed so it hangs off all four edges
+        let image = [0xaa; 10 * 10];
+        array.blit(-2, -1, 20, &image);
+
+        assert!(array.array.iter().flatten().all(|&b| b == 0xa));
+
+        // an odd width image, offset so only its bottom right corner is visible
+        let mut array = mock_array();
+        let image = [0x12, 0x30, 0x45, 0x60];
+        array.blit(14, 6, 3, &image);
+
+        assert_eq!(array.array[6][14..], [1, 2]);
+        assert_eq!(array.array[7][14..], [4, 5]);
+        let lit = array.array.iter().flatten().filter(|&&b| b != 0).count();
+        assert_eq!(lit, 4);
+    }
+
+    #[test]
+    fn test_set_pixels() {
+        let mut array = mock_array();
+
+        array.set_pixels([((0, 0), 3), ((7, 15), 12), ((8, 2), 9)]);
+
+        assert_eq!(array.get_pixel(0, 0), Some(3));
+        assert_eq!(array.get_pixel(7, 15), Some(12));
+        assert_eq!(array.get_pixel(8, 2), None);
+        let lit = array.array.iter().flatten().filter(|&&b| b != 0).count();
+        assert_eq!(lit, 2);
+    }
+
+    #[test]
+    fn test_fill_rect() {
+        let mut array = mock_array();
+
+        array.fill_rect(14, -1, 4, 3, 6);
+
+        assert_eq!(array.array[0][14..], [6, 6]);
+        assert_eq!(array.array[1][14..], [6, 6]);
+        let lit = array.array.iter().flatten().filter(|&&b| b != 0).count();
+        assert_eq!(lit, 4);
+    }
+
+    #[test]
+    fn test_draw_at_the_limits() {
+        let mut array = mock_array();
+
+        // far off the panel, nothing overflows and nothing is drawn
+        array.fill_rect(i32::MAX, 0, 2, 1, 6);
+        array.fill_rect(i32::MIN, i32::MIN, usize::MAX, 1, 6);
+        array.blit(i32::MAX, i32::MAX, 4, &[0xff; 4]);
+        array.blit_masked(i32::MAX, 0, 2, &[0xff], &[true, true]);
+        assert!(array.is_blank());
+
+        // a huge rectangle is clipped to the panel instead of looping forever
+        array.fill_rect(0, 0, usize::MAX, 1, 6);
+        assert_eq!(array.array[0], [6; NUM_COLS]);
+        array.fill_rect(-2, 1, usize::MAX, usize::MAX, 3);
+        assert!(array.array[1..].iter().flatten().all(|&b| b == 3));
+
+        // an image starting far to the left still lines up
+        array.blit(-2, 0, 4, &[0x12, 0x34]);
+        assert_eq!(array.array[0][..3], [3, 4, 6]);
+    }
+
+    #[test]
+    fn test_drawing_does_not_scan() {
+        let mut array = mock_array();
+
+        array.fill_rect(0, 0, 4, 4, 15);
+        array.set_pixel(7, 15, 3);
+        array.blit(8, 0, 2, &[0xff, 0xff]);
+
+        assert!(array.spi.written.is_empty());
+        assert_eq!(array.reg_pin.cycles, 0);
+        assert!(!array.reg_pin.state);
+        assert_eq!(array.frame_count, 0);
+    }
+
+    #[test]
+    fn test_scroll_fractional() {
+        let mut source = Frame([[0; NUM_COLS]; NUM_ROWS]);
+        source.0[2][3] = 14;
+        let mut array = mock_array();
+
+        array.scroll_fractional(&source, 0);
+        assert_eq!(array.array, source.0);
+
+        // half a column splits the pixel evenly between columns 3 and 4
+        array.scroll_fractional(&source, 128);
+        assert_eq!(array.array[2][2..6], [0, 7, 7, 0]);
+
+        array.scroll_fractional(&source, 2 * 256 + 64);
+        assert_eq!(array.array[2][4..8], [0, 11, 4, 0]);
+
+        // scrolling left past the edge clips
+        array.scroll_fractional(&source, -4 * 256);
+        assert!(array.array.iter().flatten().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_scale_rect() {
+        let mut array = mock_array();
+        array.array = [[14; NUM_COLS]; NUM_ROWS];
+
+        array.scale_rect(-1, 6, 3, 4, 128);
+
+        for row in 0..NUM_ROWS {
+            for col in 0..NUM_COLS {
+                let expected = if row >= 6 && col < 2 { 7 } else { 14 };
+                assert_eq!(array.array[row][col], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_scale_rect_clipped() {
+        let mut array = mock_array();
+        array.array = [[8; NUM_COLS]; NUM_ROWS];
+        array.set_clip(0, 0, 1, 1);
+
+        array.scale_rect(0, 0, NUM_COLS, NUM_ROWS, 0);
+        assert_eq!(array.array[0][0], 0);
+        let untouched = array.array.iter().flatten().filter(|&&b| b == 8).count();
+        assert_eq!(untouched, NUM_COLS * NUM_ROWS - 1);
+
+        // a huge rectangle can't overflow
+        array.clear_clip();
+        array.scale_rect(i32::MAX, i32::MIN, usize::MAX, usize::MAX, 0);
+        array.scale_rect(-3, -3, usize::MAX, usize::MAX, 0);
+        assert!(array.is_blank());
+    }
+
+    #[test]
+    fn test_scale_rect_transformed() {
+        let mut array = mock_array();
+        array.array = [[14; NUM_COLS]; NUM_ROWS];
+        array.transform.rotation = Rotation::Deg90;
+
+        // the top two drawing rows turn into the right two panel columns
+        array.scale_rect(0, 0, NUM_ROWS, 2, 0);
+        for row in array.array.iter() {
+            assert_eq!(row[NUM_COLS - 3..], [14, 0, 0]);
+        }
+    }
+
+    struct Gradient;
+
+    impl GrayImage for Gradient {
+        fn luma(&self, x: usize, y: usize) -> Option<u8> {
+            if x < 4 && y < 2 {
+                Some((y * 4 + x) as u8 * 32)
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn test_copy_from_image() {
+        let mut array = mock_array();
+        array.array = [[9; NUM_COLS]; NUM_ROWS];
+
+        array.copy_from_image(&Gradient);
+
+        assert_eq!(array.array[0][..5], [0, 2, 4, 6, 0]);
+        assert_eq!(array.array[1][..5], [8, 10, 12, 14, 0]);
+        assert!(array.array[2..].iter().flatten().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_apply_smoothing() {
+        let mut array = mock_array();
+
+        array.array[0][0] = 255;
+        array.apply_smoothing(128);
+        assert!((127..=128).contains(&array.array[0][0]));
+
+        // the next frame keeps pulling toward the target
+        array.array[0][0] = 255;
+        array.apply_smoothing(128);
+        assert!((190..=192).contains(&array.array[0][0]));
+
+        // and a full factor jumps straight there
+        array.array[0][0] = 0;
+        array.apply_smoothing(255);
+        assert_eq!(array.array[0][0], 0);
+        assert!(array.array.iter().flatten().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn test_gradient_fill() {
+        let mut array = mock_array();
+
+        array.gradient_fill(2, 12, Direction::Horizontal);
+        for row in array.array.iter() {
+            assert_eq!(row[0], 2);
+            assert_eq!(row[NUM_COLS - 1], 12);
+            // 2 + 10 * 7 / 15 = 6.67, and 2 + 10 * 8 / 15 = 7.33
+            assert_eq!(row[7], 7);
+            assert_eq!(row[8], 7);
+            assert!(row.windows(2).all(|pair| pair[0] <= pair[1]));
+        }
+
+        array.gradient_fill(14, 0, Direction::Vertical);
+        let mut column = [0; NUM_ROWS];
+        for (cell, row) in column.iter_mut().zip(array.array.iter()) {
+            *cell = row[3];
+        }
+        assert_eq!(column, [14, 12, 10, 8, 6, 4, 2, 0]);
+        assert!(array
+            .array
+            .iter()
+            .all(|row| row.iter().all(|&b| b == row[0])));
+    }
+
+    #[test]
+    fn test_set_led() {
+        let mut array = mock_array();
+
+        array.set_led(NUM_COLS, 5);
+        array.set_led(NUM_ROWS * NUM_COLS - 1, 6);
+        array.set_led(NUM_ROWS * NUM_COLS, 9);
+
+        assert_eq!(array.get_pixel(1, 0), Some(5));
+        assert_eq!(array.get_pixel(7, 15), Some(6));
+        let lit = array.array.iter().flatten().filter(|&&b| b != 0).count();
+        assert_eq!(lit, 2);
+    }
+
+    #[test]
+    fn test_auto_contrast() {
+        let mut array = mock_array();
+        array.array = [[7; NUM_COLS]; NUM_ROWS];
+        array.array[0][0] = 5;
+        array.array[7][15] = 10;
+
+        array.auto_contrast();
+
+        assert_eq!(array.array[0][0], 0);
+        assert_eq!(array.array[7][15], 15);
+        // 2 / 5 of the way up
+        assert_eq!(array.array[3][3], 6);
+
+        // nothing to stretch
+        array.array = [[4; NUM_COLS]; NUM_ROWS];
+        array.auto_contrast();
+        assert!(array.array.iter().flatten().all(|&b| b == 4));
+    }
+
+    #[test]
+    fn test_draw_bar() {
+        let mut array = mock_array();
+        array.array = [[1; NUM_COLS]; NUM_ROWS];
+
+        array.draw_bar(2, 4, 9);
+        for row in 0..NUM_ROWS {
+            let expected = if row >= 4 { 9 } else { 0 };
+            assert_eq!(array.array[row][2], expected);
+            // neighbouring columns are untouched
+            assert_eq!(array.array[row][1], 1);
+            assert_eq!(array.array[row][3], 1);
+        }
+
+        array.draw_bars(&[0, 8, 20], 5);
+        assert!(array.array.iter().all(|row| row[0] == 0));
+        assert!(array.array.iter().all(|row| row[1] == 5 && row[2] == 5));
+        assert!(array.array.iter().all(|row| row[3] == 1));
+    }
+
+    #[test]
+    fn test_draw_bar_transformed() {
+        let mut array = mock_array();
+        array.transform.rotation = Rotation::Deg90;
+
+        // the bottom of the 16 tall drawing area is the panel's left edge
+        array.draw_bar(2, 4, 9);
+        assert_eq!(array.array[2][..5], [9, 9, 9, 9, 0]);
+        let lit = array.array.iter().flatten().filter(|&&b| b != 0).count();
+        assert_eq!(lit, 4);
+    }
+
+    #[test]
+    fn test_blit_masked() {
+        let mut array = mock_array();
+        array.array = [[1; NUM_COLS]; NUM_ROWS];
+
+        // a 3x2 image with a transparent zero in the middle of the top row
+        let image = [0x90, 0x90, 0x99, 0x90];
+        let alpha = [true, false, true, true, true];
+        array.blit_masked(0, 0, 3, &image, &alpha);
+
+        assert_eq!(array.array[0][..4], [9, 1, 9, 1]);
+        // the last pixel has no alpha entry, so it's left out too
+        assert_eq!(array.array[1][..4], [9, 9, 1, 1]);
+    }
+
+    #[test]
+    fn test_plasma() {
+        let mut first = mock_array();
+        let mut second = mock_array();
+
+        first.plasma(42, 24);
+        second.plasma(42, 24);
+        assert_eq!(first.array, second.array);
+
+        // a full range of levels, and nothing out of range
+        let cells = first.array.iter().flatten();
+        assert!(cells.clone().all(|&b| b < 16));
+        assert!(cells.clone().any(|&b| b < 6));
+        assert!(cells.clone().any(|&b| b > 9));
+
+        second.plasma(43, 24);
+        assert_ne!(first.array, second.array);
+    }
+
+    #[test]
+    fn test_clip() {
+        let mut array = mock_array();
+        array.set_clip(2, 1, 3, 2);
+
+        array.fill_rect(0, 0, NUM_COLS, NUM_ROWS, 5);
+        array.set_pixel(0, 0, 7);
+        array.set_pixel(2, 4, 8);
+
+        for (row, cells) in array.array.iter().enumerate() {
+            for (col, &cell) in cells.iter().enumerate() {
+                let inside = (1..3).contains(&row) && (2..5).contains(&col);
+                let expected = match (row, col) {
+                    (2, 4) => 8,
+                    _ if inside => 5,
+                    _ => 0,
+                };
+                assert_eq!(cell, expected, "({}, {})", row, col);
+            }
+        }
+
+        array.clear_clip();
+        array.set_pixel(0, 0, 7);
+        assert_eq!(array.array[0][0], 7);
+
+        // sizes past the end of an i32 clip to the far edge
+        array.set_clip(i32::MAX - 1, i32::MAX, usize::MAX, usize::MAX);
+        array.set_pixel(0, 0, 1);
+        assert_eq!(array.array[0][0], 7);
+        array.set_clip(-1, -1, usize::MAX, usize::MAX);
+        array.set_pixel(NUM_ROWS - 1, NUM_COLS - 1, 9);
+        assert_eq!(array.array[NUM_ROWS - 1][NUM_COLS - 1], 9);
+    }
+
+    #[test]
+    fn test_set_pixel_rgb() {
+        let mut array = mock_array();
+
+        array.set_pixel_rgb(0, 0, 0, 200, 0);
+        array.set_pixel_rgb(0, 1, 0, 0, 200);
+        array.set_pixel_rgb(0, 2, 255, 255, 255);
+
+        assert!(array.array[0][0] > array.array[0][1]);
+        assert_eq!(array.array[0][..3], [7, 1, 15]);
+    }
+
+    #[test]
+    fn test_transform() {
+        let mut array = mock_array();
+        array.transform = Transform {
+            rotation: Rotation::Deg90,
+            mirror_x: true,
+            ..Transform::default()
+        };
+
+        // drawing is 8 wide and 16 tall, with the top left in the bottom right
+        array.set_pixel(0, 0, 5);
+        array.fill_rect(0, 15, 8, 1, 3);
+        assert_eq!(array.array[7][15], 5);
+        assert!(array.array.iter().all(|row| row[0] == 3));
+        assert_eq!(array.get_pixel(0, 0), Some(5));
+        assert_eq!(array.get_pixel(0, 8), None);
+    }
+
+    #[test]
+    fn test_draw_radial_progress() {
+        let lit = |percent| {
+            let mut array = mock_array();
+            array.draw_radial_progress(percent, 9);
+            array
+        };
+
+        // half a turn is the right half of the panel
+        let half = lit(50);
+        for row in half.array.iter() {
+            assert_eq!(row[..8], [0; 8]);
+            assert_eq!(row[8..], [9; 8]);
+        }
+
+        // a quarter is the top right corner
+        let quarter = lit(25);
+        let count = quarter.array.iter().flatten().filter(|&&b| b != 0).count();
+        assert_eq!(count, 32);
+        assert_eq!(quarter.array[3][8..], [9; 8]);
+        assert_eq!(quarter.array[4][8..], [0; 8]);
+
+        assert!(lit(0).is_blank());
+        assert!(lit(200).array.iter().flatten().all(|&b| b == 9));
+    }
+
+    #[test]
+    fn test_draw_radial_progress_transformed() {
+        let mut array = mock_array();
+        array.transform.rotation = Rotation::Deg90;
+
+        // the right half of the turned drawing area is the bottom half of
+        // the panel
+        array.draw_radial_progress(50, 9);
+        assert!(array.array[..4].iter().flatten().all(|&b| b == 0));
+        assert!(array.array[4..].iter().flatten().all(|&b| b == 9));
+    }
+}
@@ -0,0 +1,492 @@
+//! Built in fonts, all stored as `static` tables so they stay in flash.
+
+use crate::{draw, Cursor, LEDArray};
+
+/// A fixed width bitmap font.
+pub trait Font {
+    /// Width of a glyph in columns, not counting the blank column after it.
+    fn width(&self) -> usize;
+
+    /// Height of a glyph in rows.
+    fn height(&self) -> usize;
+
+    /// Whether the pixel at column `x`, row `y` of the glyph for `c` is lit.
+    /// Characters outside printable ASCII are drawn as `'?'`.
+    fn pixel(&self, c: char, x: usize, y: usize) -> bool;
+
+    /// Horizontal distance from the start of one glyph to the next.
+    fn advance(&self) -> usize {
+        self.width() + 1
+    }
+}
+
+impl<F: Font> Font for &F {
+    fn width(&self) -> usize {
+        (**self).width()
+    }
+
+    fn height(&self) -> usize {
+        (**self).height()
+    }
+
+    fn pixel(&self, c: char, x: usize, y: usize) -> bool {
+        (**self).pixel(c, x, y)
+    }
+
+    fn advance(&self) -> usize {
+        (**self).advance()
+    }
+}
+
+/// Index of `c` in a table covering `' '` to `'~'`.
+fn ascii_index(c: char) -> usize {
+    match c {
+        ' '..='~' => c as usize - ' ' as usize,
+        _ => '?' as usize - ' ' as usize,
+    }
+}
+
+/// A tiny 3x5 font. Lowercase letters are drawn as capitals, and symbols
+/// without a glyph as `'?'`.
+pub struct Font3x5;
+
+/// Printable ASCII, one byte per column with the top row in the least
+/// significant bit.
+static FONT_3X5: [[u8; 3]; 95] = [
+    [0x00, 0x00, 0x00], // ' '
+    [0x00, 0x17, 0x00], // !
+    [0x01, 0x15, 0x03], // " (as ?)
+    [0x01, 0x15, 0x03], // # (as ?)
+    [0x01, 0x15, 0x03], // $ (as ?)
+    [0x19, 0x04, 0x13], // %
+    [0x01, 0x15, 0x03], // & (as ?)
+    [0x01, 0x15, 0x03], // ' (as ?)
+    [0x0e, 0x11, 0x00], // (
+    [0x00, 0x11, 0x0e], // )
+    [0x01, 0x15, 0x03], // * (as ?)
+    [0x04, 0x0e, 0x04], // +
+    [0x10, 0x08, 0x00], // ,
+    [0x04, 0x04, 0x04], // -
+    [0x00, 0x10, 0x00], // .
+    [0x18, 0x04, 0x03], // /
+    [0x1f, 0x11, 0x1f], // 0
+    [0x12, 0x1f, 0x10], // 1
+    [0x1d, 0x15, 0x17], // 2
+    [0x15, 0x15, 0x1f], // 3
+    [0x07, 0x04, 0x1f], // 4
+    [0x17, 0x15, 0x1d], // 5
+    [0x1f, 0x15, 0x1d], // 6
+    [0x01, 0x01, 0x1f], // 7
+    [0x1f, 0x15, 0x1f], // 8
+    [0x17, 0x15, 0x1f], // 9
+    [0x00, 0x0a, 0x00], // :
+    [0x01, 0x15, 0x03], // ; (as ?)
+    [0x01, 0x15, 0x03], // < (as ?)
+    [0x0a, 0x0a, 0x0a], // =
+    [0x01, 0x15, 0x03], // > (as ?)
+    [0x01, 0x15, 0x03], // ?
+    [0x01, 0x15, 0x03], // @ (as ?)
+    [0x1e, 0x05, 0x1e], // A
+    [0x1f, 0x15, 0x0a], // B
+    [0x0e, 0x11, 0x11], // C
+    [0x1f, 0x11, 0x0e], // D
+    [0x1f, 0x15, 0x11], // E
+    [0x1f, 0x05, 0x01], // F
+    [0x0e, 0x11, 0x1d], // G
+    [0x1f, 0x04, 0x1f], // H
+    [0x11, 0x1f, 0x11], // I
+    [0x08, 0x10, 0x0f], // J
+    [0x1f, 0x04, 0x1b], // K
+    [0x1f, 0x10, 0x10], // L
+    [0x1f, 0x06, 0x1f], // M
+    [0x1f, 0x01, 0x1e], // N
+    [0x0e, 0x11, 0x0e], // O
+    [0x1f, 0x05, 0x02], // P
+    [0x0e, 0x19, 0x16], // Q
+    [0x1f, 0x05, 0x1a], // R
+    [0x12, 0x15, 0x09], // S
+    [0x01, 0x1f, 0x01], // T
+    [0x1f, 0x10, 0x1f], // U
+    [0x0f, 0x10, 0x0f], // V
+    [0x1f, 0x0c, 0x1f], // W
+    [0x1b, 0x04, 0x1b], // X
+    [0x03, 0x1c, 0x03], // Y
+    [0x19, 0x15, 0x13], // Z
+    [0x01, 0x15, 0x03], // [ (as ?)
+    [0x01, 0x15, 0x03], // \ (as ?)
+    [0x01, 0x15, 0x03], // ] (as ?)
+    [0x01, 0x15, 0x03], // ^ (as ?)
+    [0x01, 0x15, 0x03], // _ (as ?)
+    [0x01, 0x15, 0x03], // ` (as ?)
+    [0x1e, 0x05, 0x1e], // a (as A)
+    [0x1f, 0x15, 0x0a], // b (as B)
+    [0x0e, 0x11, 0x11], // c (as C)
+    [0x1f, 0x11, 0x0e], // d (as D)
+    [0x1f, 0x15, 0x11], // e (as E)
+    [0x1f, 0x05, 0x01], // f (as F)
+    [0x0e, 0x11, 0x1d], // g (as G)
+    [0x1f, 0x04, 0x1f], // h (as H)
+    [0x11, 0x1f, 0x11], // i (as I)
+    [0x08, 0x10, 0x0f], // j (as J)
+    [0x1f, 0x04, 0x1b], // k (as K)
+    [0x1f, 0x10, 0x10], // l (as L)
+    [0x1f, 0x06, 0x1f], // m (as M)
+    [0x1f, 0x01, 0x1e], // n (as N)
+    [0x0e, 0x11, 0x0e], // o (as O)
+    [0x1f, 0x05, 0x02], // p (as P)
+    [0x0e, 0x19, 0x16], // q (as Q)
+    [0x1f, 0x05, 0x1a], // r (as R)
+    [0x12, 0x15, 0x09], // s (as S)
+    [0x01, 0x1f, 0x01], // t (as T)
+    [0x1f, 0x10, 0x1f], // u (as U)
+    [0x0f, 0x10, 0x0f], // v (as V)
+    [0x1f, 0x0c, 0x1f], // w (as W)
+    [0x1b, 0x04, 0x1b], // x (as X)
+    [0x03, 0x1c, 0x03], // y (as Y)
+    [0x19, 0x15, 0x13], // z (as Z)
+    [0x01, 0x15, 0x03], // { (as ?)
+    [0x01, 0x15, 0x03], // | (as ?)
+    [0x01, 0x15, 0x03], // } (as ?)
+    [0x01, 0x15, 0x03], // ~ (as ?)
+];
+
+impl Font for Font3x5 {
+    fn width(&self) -> usize {
+        3
+    }
+
+    fn height(&self) -> usize {
+        5
+    }
+
+    fn pixel(&self, c: char, x: usize, y: usize) -> bool {
+        let column = FONT_3X5[ascii_index(c)].get(x).copied().unwrap_or(0);
+        y < 5 && (column >> y) & 1 == 1
+    }
+}
+
+/// The default 5x7 font, which fits the height of the panel.
+pub struct Font5x7;
+
+/// Printable ASCII, one byte per column with the top row in the least
+/// significant bit.
+static FONT_5X7: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5f, 0x00, 0x00], // !
+    [0x00, 0x07, 0x00, 0x07, 0x00], // "
+    [0x14, 0x7f, 0x14, 0x7f, 0x14], // #
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12], // $
+    [0x23, 0x13, 0x08, 0x64, 0x62], // %
+    [0x36, 0x49, 0x55, 0x22, 0x50], // &
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '
+    [0x00, 0x1c, 0x22, 0x41, 0x00], // (
+    [0x00, 0x41, 0x22, 0x1c, 0x00], // )
+    [0x08, 0x2a, 0x1c, 0x2a, 0x08], // *
+    [0x08, 0x08, 0x3e, 0x08, 0x08], // +
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ,
+    [0x08, 0x08, 0x08, 0x08, 0x08], // -
+    [0x00, 0x60, 0x60, 0x00, 0x00], // .
+    [0x20, 0x10, 0x08, 0x04, 0x02], // /
+    [0x3e, 0x51, 0x49, 0x45, 0x3e], // 0
+    [0x00, 0x42, 0x7f, 0x40, 0x00], // 1
+    [0x42, 0x61, 0x51, 0x49, 0x46], // 2
+    [0x21, 0x41, 0x45, 0x4b, 0x31], // 3
+    [0x18, 0x14, 0x12, 0x7f, 0x10], // 4
+    [0x27, 0x45, 0x45, 0x45, 0x39], // 5
+    [0x3c, 0x4a, 0x49, 0x49, 0x30], // 6
+    [0x01, 0x71, 0x09, 0x05, 0x03], // 7
+    [0x36, 0x49, 0x49, 0x49, 0x36], // 8
+    [0x06, 0x49, 0x49, 0x29, 0x1e], // 9
+    [0x00, 0x36, 0x36, 0x00, 0x00], // :
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ;
+    [0x00, 0x08, 0x14, 0x22, 0x41], // <
+    [0x14, 0x14, 0x14, 0x14, 0x14], // =
+    [0x41, 0x22, 0x14, 0x08, 0x00], // >
+    [0x02, 0x01, 0x51, 0x09, 0x06], // ?
+    [0x32, 0x49, 0x79, 0x41, 0x3e], // @
+    [0x7e, 0x11, 0x11, 0x11, 0x7e], // A
+    [0x7f, 0x49, 0x49, 0x49, 0x36], // B
+    [0x3e, 0x41, 0x41, 0x41, 0x22], // C
+    [0x7f, 0x41, 0x41, 0x22, 0x1c], // D
+    [0x7f, 0x49, 0x49, 0x49, 0x41], // E
+    [0x7f, 0x09, 0x09, 0x01, 0x01], // F
+    [0x3e, 0x41, 0x41, 0x51, 0x32], // G
+    [0x7f, 0x08, 0x08, 0x08, 0x7f], // H
+    [0x00, 0x41, 0x7f, 0x41, 0x00], // I
+    [0x20, 0x40, 0x41, 0x3f, 0x01], // J
+    [0x7f, 0x08, 0x14, 0x22, 0x41], // K
+    [0x7f, 0x40, 0x40, 0x40, 0x40], // L
+    [0x7f, 0x02, 0x04, 0x02, 0x7f], // M
+    [0x7f, 0x04, 0x08, 0x10, 0x7f], // N
+    [0x3e, 0x41, 0x41, 0x41, 0x3e], // O
+    [0x7f, 0x09, 0x09, 0x09, 0x06], // P
+    [0x3e, 0x41, 0x51, 0x21, 0x5e], // Q
+    [0x7f, 0x09, 0x19, 0x29, 0x46], // R
+    [0x46, 0x49, 0x49, 0x49, 0x31], // S
+    [0x01, 0x01, 0x7f, 0x01, 0x01], // T
+    [0x3f, 0x40, 0x40, 0x40, 0x3f], // U
+    [0x1f, 0x20, 0x40, 0x20, 0x1f], // V
+    [0x7f, 0x20, 0x18, 0x20, 0x7f], // W
+    [0x63, 0x14, 0x08, 0x14, 0x63], // X
+    [0x03, 0x04, 0x78, 0x04, 0x03], // Y
+    [0x61, 0x51, 0x49, 0x45, 0x43], // Z
+    [0x00, 0x00, 0x7f, 0x41, 0x41], // [
+    [0x02, 0x04, 0x08, 0x10, 0x20], // \
+    [0x41, 0x41, 0x7f, 0x00, 0x00], // ]
+    [0x04, 0x02, 0x01, 0x02, 0x04], // ^
+    [0x40, 0x40, 0x40, 0x40, 0x40], // _
+    [0x00, 0x01, 0x02, 0x04, 0x00], // `
+    [0x20, 0x54, 0x54, 0x54, 0x78], // a
+    [0x7f, 0x48, 0x44, 0x44, 0x38], // b
+    [0x38, 0x44, 0x44, 0x44, 0x20], // c
+    [0x38, 0x44, 0x44, 0x48, 0x7f], // d
+    [0x38, 0x54, 0x54, 0x54, 0x18], // e
+    [0x08, 0x7e, 0x09, 0x01, 0x02], // f
+    [0x08, 0x14, 0x54, 0x54, 0x3c], // g
+    [0x7f, 0x08, 0x04, 0x04, 0x78], // h
+    [0x00, 0x44, 0x7d, 0x40, 0x00], // i
+    [0x20, 0x40, 0x44, 0x3d, 0x00], // j
+    [0x00, 0x7f, 0x10, 0x28, 0x44], // k
+    [0x00, 0x41, 0x7f, 0x40, 0x00], // l
+    [0x7c, 0x04, 0x18, 0x04, 0x78], // m
+    [0x7c, 0x08, 0x04, 0x04, 0x78], // n
+    [0x38, 0x44, 0x44, 0x44, 0x38], // o
+    [0x7c, 0x14, 0x14, 0x14, 0x08], // p
+    [0x08, 0x14, 0x14, 0x18, 0x7c], // q
+    [0x7c, 0x08, 0x04, 0x04, 0x08], // r
+    [0x48, 0x54, 0x54, 0x54, 0x20], // s
+    [0x04, 0x3f, 0x44, 0x40, 0x20], // t
+    [0x3c, 0x40, 0x40, 0x20, 0x7c], // u
+    [0x1c, 0x20, 0x40, 0x20, 0x1c], // v
+    [0x3c, 0x40, 0x30, 0x40, 0x3c], // w
+    [0x44, 0x28, 0x10, 0x28, 0x44], // x
+    [0x0c, 0x50, 0x50, 0x50, 0x3c], // y
+    [0x44, 0x64, 0x54, 0x4c, 0x44], // z
+    [0x00, 0x08, 0x36, 0x41, 0x00], // {
+    [0x00, 0x00, 0x7f, 0x00, 0x00], // |
+    [0x00, 0x41, 0x36, 0x08, 0x00], // }
+    [0x10, 0x08, 0x08, 0x10, 0x08], // ~
+];
+
+impl Font for Font5x7 {
+    fn width(&self) -> usize {
+        5
+    }
+
+    fn height(&self) -> usize {
+        7
+    }
+
+    fn pixel(&self, c: char, x: usize, y: usize) -> bool {
+        let column = FONT_5X7[ascii_index(c)].get(x).copied().unwrap_or(0);
+        y < 7 && (column >> y) & 1 == 1
+    }
+}
+
+/// A full height 8x8 font, in the style of the IBM PC BIOS font.
+pub struct Font8x8;
+
+/// Printable ASCII, one byte per row with the leftmost column in the least
+/// significant bit.
+static FONT_8X8: [[u8; 8]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x18, 0x3c, 0x3c, 0x18, 0x18, 0x00, 0x18, 0x00], // !
+    [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // "
+    [0x36, 0x36, 0x7f, 0x36, 0x7f, 0x36, 0x36, 0x00], // #
+    [0x0c, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x0c, 0x00], // $
+    [0x00, 0x63, 0x33, 0x18, 0x0c, 0x66, 0x63, 0x00], // %
+    [0x1c, 0x36, 0x1c, 0x6e, 0x3b, 0x33, 0x6e, 0x00], // &
+    [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // '
+    [0x18, 0x0c, 0x06, 0x06, 0x06, 0x0c, 0x18, 0x00], // (
+    [0x06, 0x0c, 0x18, 0x18, 0x18, 0x0c, 0x06, 0x00], // )
+    [0x00, 0x66, 0x3c, 0xff, 0x3c, 0x66, 0x00, 0x00], // *
+    [0x00, 0x0c, 0x0c, 0x3f, 0x0c, 0x0c, 0x00, 0x00], // +
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x06], // ,
+    [0x00, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x00, 0x00], // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00], // .
+    [0x60, 0x30, 0x18, 0x0c, 0x06, 0x03, 0x01, 0x00], // /
+    [0x3e, 0x63, 0x73, 0x7b, 0x6f, 0x67, 0x3e, 0x00], // 0
+    [0x0c, 0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x3f, 0x00], // 1
+    [0x1e, 0x33, 0x30, 0x1c, 0x06, 0x33, 0x3f, 0x00], // 2
+    [0x1e, 0x33, 0x30, 0x1c, 0x30, 0x33, 0x1e, 0x00], // 3
+    [0x38, 0x3c, 0x36, 0x33, 0x7f, 0x30, 0x78, 0x00], // 4
+    [0x3f, 0x03, 0x1f, 0x30, 0x30, 0x33, 0x1e, 0x00], // 5
+    [0x1c, 0x06, 0x03, 0x1f, 0x33, 0x33, 0x1e, 0x00], // 6
+    [0x3f, 0x33, 0x30, 0x18, 0x0c, 0x0c, 0x0c, 0x00], // 7
+    [0x1e, 0x33, 0x33, 0x1e, 0x33, 0x33, 0x1e, 0x00], // 8
+    [0x1e, 0x33, 0x33, 0x3e, 0x30, 0x18, 0x0e, 0x00], // 9
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x00], // :
+    [0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x06], // ;
+    [0x18, 0x0c, 0x06, 0x03, 0x06, 0x0c, 0x18, 0x00], // <
+    [0x00, 0x00, 0x3f, 0x00, 0x00, 0x3f, 0x00, 0x00], // =
+    [0x06, 0x0c, 0x18, 0x30, 0x18, 0x0c, 0x06, 0x00], // >
+    [0x1e, 0x33, 0x30, 0x18, 0x0c, 0x00, 0x0c, 0x00], // ?
+    [0x3e, 0x63, 0x7b, 0x7b, 0x7b, 0x03, 0x1e, 0x00], // @
+    [0x0c, 0x1e, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x00], // A
+    [0x3f, 0x66, 0x66, 0x3e, 0x66, 0x66, 0x3f, 0x00], // B
+    [0x3c, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3c, 0x00], // C
+    [0x1f, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1f, 0x00], // D
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x46, 0x7f, 0x00], // E
+    [0x7f, 0x46, 0x16, 0x1e, 0x16, 0x06, 0x0f, 0x00], // F
+    [0x3c, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7c, 0x00], // G
+    [0x33, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x33, 0x00], // H
+    [0x1e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // I
+    [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e, 0x00], // J
+    [0x67, 0x66, 0x36, 0x1e, 0x36, 0x66, 0x67, 0x00], // K
+    [0x0f, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7f, 0x00], // L
+    [0x63, 0x77, 0x7f, 0x7f, 0x6b, 0x63, 0x63, 0x00], // M
+    [0x63, 0x67, 0x6f, 0x7b, 0x73, 0x63, 0x63, 0x00], // N
+    [0x1c, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1c, 0x00], // O
+    [0x3f, 0x66, 0x66, 0x3e, 0x06, 0x06, 0x0f, 0x00], // P
+    [0x1e, 0x33, 0x33, 0x33, 0x3b, 0x1e, 0x38, 0x00], // Q
+    [0x3f, 0x66, 0x66, 0x3e, 0x36, 0x66, 0x67, 0x00], // R
+    [0x1e, 0x33, 0x07, 0x0e, 0x38, 0x33, 0x1e, 0x00], // S
+    [0x3f, 0x2d, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // T
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3f, 0x00], // U
+    [0x33, 0x33, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // V
+    [0x63, 0x63, 0x63, 0x6b, 0x7f, 0x77, 0x63, 0x00], // W
+    [0x63, 0x63, 0x36, 0x1c, 0x1c, 0x36, 0x63, 0x00], // X
+    [0x33, 0x33, 0x33, 0x1e, 0x0c, 0x0c, 0x1e, 0x00], // Y
+    [0x7f, 0x63, 0x31, 0x18, 0x4c, 0x66, 0x7f, 0x00], // Z
+    [0x1e, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1e, 0x00], // [
+    [0x03, 0x06, 0x0c, 0x18, 0x30, 0x60, 0x40, 0x00], // \
+    [0x1e, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1e, 0x00], // ]
+    [0x08, 0x1c, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff], // _
+    [0x0c, 0x0c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // `
+    [0x00, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x6e, 0x00], // a
+    [0x07, 0x06, 0x06, 0x3e, 0x66, 0x66, 0x3b, 0x00], // b
+    [0x00, 0x00, 0x1e, 0x33, 0x03, 0x33, 0x1e, 0x00], // c
+    [0x38, 0x30, 0x30, 0x3e, 0x33, 0x33, 0x6e, 0x00], // d
+    [0x00, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00], // e
+    [0x1c, 0x36, 0x06, 0x0f, 0x06, 0x06, 0x0f, 0x00], // f
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x1f], // g
+    [0x07, 0x06, 0x36, 0x6e, 0x66, 0x66, 0x67, 0x00], // h
+    [0x0c, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // i
+    [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e], // j
+    [0x07, 0x06, 0x66, 0x36, 0x1e, 0x36, 0x67, 0x00], // k
+    [0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // l
+    [0x00, 0x00, 0x33, 0x7f, 0x7f, 0x6b, 0x63, 0x00], // m
+    [0x00, 0x00, 0x1f, 0x33, 0x33, 0x33, 0x33, 0x00], // n
+    [0x00, 0x00, 0x1e, 0x33, 0x33, 0x33, 0x1e, 0x00], // o
+    [0x00, 0x00, 0x3b, 0x66, 0x66, 0x3e, 0x06, 0x0f], // p
+    [0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x78], // q
+    [0x00, 0x00, 0x3b, 0x6e, 0x66, 0x06, 0x0f, 0x00], // r
+    [0x00, 0x00, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x00], // s
+    [0x08, 0x0c, 0x3e, 0x0c, 0x0c, 0x2c, 0x18, 0x00], // t
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6e, 0x00], // u
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // v
+    [0x00, 0x00, 0x63, 0x6b, 0x7f, 0x7f, 0x36, 0x00], // w
+    [0x00, 0x00, 0x63, 0x36, 0x1c, 0x36, 0x63, 0x00], // x
+    [0x00, 0x00, 0x33, 0x33, 0x33, 0x3e, 0x30, 0x1f], // y
+    [0x00, 0x00, 0x3f, 0x19, 0x0c, 0x26, 0x3f, 0x00], // z
+    [0x38, 0x0c, 0x0c, 0x07, 0x0c, 0x0c, 0x38, 0x00], // {
+    [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // |
+    [0x07, 0x0c, 0x0c, 0x38, 0x0c, 0x0c, 0x07, 0x00], // }
+    [0x6e, 0x3b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ~
+];
+
+impl Font for Font8x8 {
+    fn width(&self) -> usize {
+        8
+    }
+
+    fn height(&self) -> usize {
+        8
+    }
+
+    fn pixel(&self, c: char, x: usize, y: usize) -> bool {
+        let row = FONT_8X8[ascii_index(c)].get(y).copied().unwrap_or(0);
+        x < 8 && (row >> x) & 1 == 1
+    }
+}
+
+impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    /// Draw `c` in `font` with its top left corner at `(x, y)`. Only the lit
+    /// pixels of the glyph are written, and anything off the panel is clipped.
+    pub fn draw_char_with<F>(&mut self, font: &F, x: i32, y: i32, c: char, brightness: u8)
+    where
+        F: Font,
+    {
+        let area = self.transform.size();
+        let pixels = draw::rect_offsets(area, x, y, font.width(), font.height())
+            .filter(|&(_, (dx, dy))| font.pixel(c, dx, dy))
+            .map(|(at, _)| (at, brightness));
+        self.draw_iter(pixels);
+    }
+
+    /// Draw `text` in `font` starting at `(x, y)`, returning the `x` just past
+    /// the end. Text runs straight off the right edge rather than wrapping;
+    /// use a [`Cursor`] with `draw_text` for that.
+    pub fn draw_str_with<F>(&mut self, font: &F, x: i32, y: i32, text: &str, brightness: u8) -> i32
+    where
+        F: Font,
+    {
+        let mut cursor = Cursor::new(font, x, y, brightness);
+        cursor.wrap = false;
+        self.draw_text(&mut cursor, text);
+        cursor.x
+    }
+
+    /// Draw `c` in the default 5x7 font.
+    pub fn draw_char(&mut self, x: i32, y: i32, c: char, brightness: u8) {
+        self.draw_char_with(&Font5x7, x, y, c, brightness);
+    }
+
+    /// Draw `text` in the default 5x7 font, returning the `x` just past the
+    /// end.
+    pub fn draw_str(&mut self, x: i32, y: i32, text: &str, brightness: u8) -> i32 {
+        self.draw_str_with(&Font5x7, x, y, text, brightness)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_array;
+
+    #[test]
+    fn test_draw_str() {
+        let mut array = mock_array();
+
+        let end = array.draw_str(0, 0, "I-", 5);
+        assert_eq!(end, 12);
+
+        // the stem of the I
+        for row in 0..Font5x7.height() {
+            assert_eq!(array.array[row][2], 5);
+        }
+        assert_eq!(array.array[7][2], 0);
+        // the bar of the -
+        assert_eq!(array.array[3][6..11], [5; 5]);
+        assert_eq!(array.array[2][6..11], [0; 5]);
+    }
+
+    #[test]
+    fn test_draw_char_at_the_limits() {
+        let mut array = mock_array();
+        array.draw_char(i32::MAX, i32::MAX, 'A', 5);
+        array.draw_char(i32::MIN, 0, 'A', 5);
+        assert!(array.is_blank());
+
+        // half off the left edge, the stem lands in the first column
+        array.draw_char(-2, 0, 'I', 5);
+        assert_eq!(array.array[3][..2], [5, 0]);
+    }
+
+    fn lit_cells<F: Font>(font: &F, c: char) -> usize {
+        let mut array = mock_array();
+        array.draw_char_with(font, 0, 0, c, 1);
+        array.array.iter().flatten().filter(|&&b| b != 0).count()
+    }
+
+    #[test]
+    fn test_fonts_differ() {
+        assert_eq!(lit_cells(&Font3x5, 'A'), 10);
+        assert_eq!(lit_cells(&Font5x7, 'A'), 18);
+        assert_eq!(lit_cells(&Font8x8, 'A'), 28);
+
+        // the small font has no lowercase
+        assert_eq!(lit_cells(&Font3x5, 'a'), 10);
+    }
+}
@@ -0,0 +1,577 @@
+use core::convert::TryFrom;
+
+use crate::hal;
+use crate::{scale, Direction, LEDArray, LEDError, LAYER_BITS, NUM_COLS, NUM_ROWS};
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+/// A whole framebuffer of brightness values, indexed `[row][col]`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Frame(pub [[u8; NUM_COLS]; NUM_ROWS]);
+
+impl Frame {
+    /// Every pixel off.
+    pub const fn zeroed() -> Self {
+        Frame([[0; NUM_COLS]; NUM_ROWS])
+    }
+
+    /// Every pixel at `brightness`.
+    pub const fn filled(brightness: u8) -> Self {
+        Frame([[brightness; NUM_COLS]; NUM_ROWS])
+    }
+
+    /// Alternate pixels at full brightness, starting with the top left one.
+    pub const fn checkerboard() -> Self {
+        let mut frame = [[0; NUM_COLS]; NUM_ROWS];
+        let mut row = 0;
+        while row < NUM_ROWS {
+            let mut col = 0;
+            while col < NUM_COLS {
+                if (row + col) % 2 == 0 {
+                    frame[row][col] = (1 << LAYER_BITS) - 1;
+                }
+                col += 1;
+            }
+            row += 1;
+        }
+        Frame(frame)
+    }
+}
+
+/// A byte slice wasn't the `NUM_ROWS * NUM_COLS` bytes a frame needs.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WrongLength {
+    pub expected: usize,
+    pub got: usize,
+}
+
+/// Read a frame from `NUM_ROWS * NUM_COLS` bytes of brightness, one row after
+/// another.
+impl TryFrom<&[u8]> for Frame {
+    type Error = WrongLength;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        if bytes.len() != NUM_ROWS * NUM_COLS {
+            return Err(WrongLength {
+                expected: NUM_ROWS * NUM_COLS,
+                got: bytes.len(),
+            });
+        }
+
+        let mut frame = Frame([[0; NUM_COLS]; NUM_ROWS]);
+        for (row, line) in frame.0.iter_mut().zip(bytes.chunks(NUM_COLS)) {
+            row.copy_from_slice(line);
+        }
+        Ok(frame)
+    }
+}
+
+/// The length of a frame written by `dump_frame_checked`: one byte per pixel,
+/// row after row, then a CRC-8.
+pub const CHECKED_FRAME_LEN: usize = NUM_ROWS * NUM_COLS + 1;
+
+/// Why `load_frame_checked` turned a frame down.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChecksumError {
+    WrongLength(WrongLength),
+    /// The CRC at the end didn't match the pixels, so they were corrupted.
+    Mismatch {
+        expected: u8,
+        got: u8,
+    },
+}
+
+impl From<WrongLength> for ChecksumError {
+    fn from(error: WrongLength) -> Self {
+        ChecksumError::WrongLength(error)
+    }
+}
+
+/// CRC-8 with the polynomial x^8 + x^2 + x + 1 (0x07), starting from 0.
+fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in bytes {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ 0x07
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// How `blend` combines an overlay with what's already in the array.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlendMode {
+    /// The brighter of the two.
+    Max,
+    /// The sum, capped at the top brightness level.
+    Add,
+    /// The dimmer of the two.
+    Min,
+    /// The overlay wherever it isn't off, otherwise the array.
+    Over,
+}
+
+impl BlendMode {
+    fn apply(self, under: u8, over: u8) -> u8 {
+        match self {
+            BlendMode::Max => under.max(over),
+            BlendMode::Add => under.saturating_add(over).min((1 << LAYER_BITS) - 1),
+            BlendMode::Min => under.min(over),
+            BlendMode::Over => {
+                if over != 0 {
+                    over
+                } else {
+                    under
+                }
+            }
+        }
+    }
+}
+
+/// Somewhere frames can be streamed from, such as flash or an SD card.
+pub trait FrameSource {
+    type Error;
+
+    /// Fill `out` with the next frame, or return `false` once there are none
+    /// left.
+    fn next_frame(&mut self, out: &mut Frame) -> Result<bool, Self::Error>;
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PlayError<E, P, S> {
+    /// The frame source failed.
+    Source(E),
+    /// Showing a frame failed.
+    Display(LEDError<P, S>),
+}
+
+impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    /// Copy `frame` into the framebuffer.
+    pub fn load_frame(&mut self, frame: &Frame) {
+        self.touch();
+        self.array = frame.0;
+    }
+
+    /// Load a frame sent over an unreliable link as `CHECKED_FRAME_LEN` bytes
+    /// from `dump_frame_checked`. If the length or the CRC is wrong `array`
+    /// is left as it was.
+    pub fn load_frame_checked(&mut self, bytes: &[u8]) -> Result<(), ChecksumError> {
+        let (&got, pixels) = bytes.split_last().ok_or(WrongLength {
+            expected: CHECKED_FRAME_LEN,
+            got: 0,
+        })?;
+        let frame = Frame::try_from(pixels).map_err(|_| WrongLength {
+            expected: CHECKED_FRAME_LEN,
+            got: bytes.len(),
+        })?;
+
+        let expected = crc8(pixels);
+        if got != expected {
+            return Err(ChecksumError::Mismatch { expected, got });
+        }
+        self.load_frame(&frame);
+        Ok(())
+    }
+
+    /// Write `array` into `out` for `load_frame_checked`: one byte per pixel,
+    /// row after row, followed by their CRC-8.
+    pub fn dump_frame_checked(&self, out: &mut [u8; CHECKED_FRAME_LEN]) {
+        for (chunk, row) in out.chunks_mut(NUM_COLS).zip(self.array.iter()) {
+            chunk.copy_from_slice(row);
+        }
+        let crc = crc8(&out[..CHECKED_FRAME_LEN - 1]);
+        out[CHECKED_FRAME_LEN - 1] = crc;
+    }
+
+    /// Combine `overlay` into `array` pixel by pixel, e.g. to put a HUD over
+    /// other content.
+    pub fn blend(&mut self, overlay: &Frame, mode: BlendMode) {
+        self.blend_cells(overlay, None, mode);
+    }
+
+    /// `blend`, leaving the array alone wherever `alpha` is `false` so the
+    /// overlay can be transparent without treating off pixels as see through.
+    pub fn blend_masked(
+        &mut self,
+        overlay: &Frame,
+        alpha: &[[bool; NUM_COLS]; NUM_ROWS],
+        mode: BlendMode,
+    ) {
+        self.blend_cells(overlay, Some(alpha), mode);
+    }
+
+    fn blend_cells(
+        &mut self,
+        overlay: &Frame,
+        alpha: Option<&[[bool; NUM_COLS]; NUM_ROWS]>,
+        mode: BlendMode,
+    ) {
+        self.touch();
+        for (r, (row, over_row)) in self.array.iter_mut().zip(overlay.0.iter()).enumerate() {
+            for (c, (cell, &over)) in row.iter_mut().zip(over_row.iter()).enumerate() {
+                if alpha.is_some_and(|alpha| !alpha[r][c]) {
+                    continue;
+                }
+                *cell = mode.apply(*cell, over);
+            }
+        }
+    }
+
+    /// Reveal `target` over the current contents `step` columns at a time
+    /// from the left, or `step` rows at a time from the top for a vertical
+    /// wipe. Call it once per frame; it returns `true` once the whole of
+    /// `target` is showing, and the next call starts a new wipe. A `step` of 0
+    /// moves one column or row at a time, so the wipe always finishes.
+    pub fn wipe_to(&mut self, target: &Frame, direction: Direction, step: usize) -> bool {
+        let total = match direction {
+            Direction::Horizontal => NUM_COLS,
+            Direction::Vertical => NUM_ROWS,
+        };
+        let start = self.wipe_progress.min(total);
+        let end = start.saturating_add(step.max(1)).min(total);
+        self.touch();
+
+        for (r, (row, target_row)) in self.array.iter_mut().zip(target.0.iter()).enumerate() {
+            match direction {
+                Direction::Horizontal => {
+                    let cells = row.iter_mut().zip(target_row.iter());
+                    for (cell, &value) in cells.skip(start).take(end - start) {
+                        *cell = value;
+                    }
+                }
+                Direction::Vertical if (start..end).contains(&r) => *row = *target_row,
+                Direction::Vertical => {}
+            }
+        }
+
+        if end == total {
+            self.wipe_progress = 0;
+            true
+        } else {
+            self.wipe_progress = end;
+            false
+        }
+    }
+
+    /// Show each of `frames` in turn for `frames_per` scans, for animations
+    /// built at compile time and kept in flash. Frames are copied into
+    /// `array` one at a time as they come up, so only one is ever in RAM.
+    pub fn play_static<T, PinError>(
+        &mut self,
+        frames: &'static [[[u8; NUM_COLS]; NUM_ROWS]],
+        base_freq: T,
+        frames_per: u32,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        self.play_static_with_envelope(frames, base_freq, frames_per, &[])
+    }
+
+    /// Like `play_static`, but scaling `brightness` through `envelope` over
+    /// each frame's `frames_per` scans, so frames can fade in and out. The
+    /// envelope is stretched over the scans, scan `i` using entry
+    /// `i * envelope.len() / frames_per`, and each entry scales `brightness`
+    /// by `entry / 255`. An empty envelope leaves it alone. `brightness` is
+    /// put back afterwards, even on an error.
+    pub fn play_static_with_envelope<T, PinError>(
+        &mut self,
+        frames: &'static [[[u8; NUM_COLS]; NUM_ROWS]],
+        base_freq: T,
+        frames_per: u32,
+        envelope: &[u8],
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        let brightness = self.brightness;
+        let result = frames.iter().try_for_each(|frame| {
+            self.touch();
+            self.array = *frame;
+            (0..frames_per).try_for_each(|i| {
+                let entry = i as usize * envelope.len() / frames_per as usize;
+                if let Some(&factor) = envelope.get(entry) {
+                    self.brightness = scale(brightness, factor);
+                }
+                self.scan(base_freq)
+            })
+        });
+        self.brightness = brightness;
+        result
+    }
+
+    /// Show every frame from `source` in turn, one scan each.
+    pub fn play<F, T, PinError>(
+        &mut self,
+        source: &mut F,
+        base_freq: T,
+    ) -> Result<(), PlayError<F::Error, PinError, SPI::Error>>
+    where
+        F: FrameSource,
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        let mut frame = Frame(self.array);
+        while source.next_frame(&mut frame).map_err(PlayError::Source)? {
+            self.load_frame(&frame);
+            self.scan(base_freq).map_err(PlayError::Display)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_array;
+
+    struct TwoFrames {
+        sent: usize,
+    }
+
+    impl FrameSource for TwoFrames {
+        type Error = ();
+
+        fn next_frame(&mut self, out: &mut Frame) -> Result<bool, ()> {
+            let brightness = match self.sent {
+                0 => 15,
+                1 => 0,
+                _ => return Ok(false),
+            };
+            self.sent += 1;
+            *out = Frame([[brightness; NUM_COLS]; NUM_ROWS]);
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_play() {
+        let mut array = mock_array();
+        let mut source = TwoFrames { sent: 0 };
+
+        assert!(array.play(&mut source, 1).is_ok());
+
+        assert_eq!(array.frame_count, 2);
+        let (first, second) = array.spi.written.split_at(64);
+        assert!(first.iter().all(|&b| b == 0x00));
+        assert!(second.iter().all(|&b| b == 0xff));
+    }
+
+    #[test]
+    fn test_play_static() {
+        static FRAMES: [[[u8; NUM_COLS]; NUM_ROWS]; 2] =
+            [[[15; NUM_COLS]; NUM_ROWS], [[0; NUM_COLS]; NUM_ROWS]];
+        let mut array = mock_array();
+
+        assert!(array.play_static(&FRAMES, 1, 2).is_ok());
+
+        assert_eq!(array.frame_count, 4);
+        let (first, second) = array.spi.written.split_at(128);
+        assert!(first.iter().all(|&b| b == 0x00));
+        assert!(second.iter().all(|&b| b == 0xff));
+        assert_eq!(array.array, FRAMES[1]);
+    }
+
+    #[test]
+    fn test_play_static_with_envelope() {
+        static FRAMES: [[[u8; NUM_COLS]; NUM_ROWS]; 1] = [[[15; NUM_COLS]; NUM_ROWS]];
+        let mut array = mock_array();
+        array.brightness = 255;
+
+        // fade in over four scans, two per envelope entry
+        assert!(array
+            .play_static_with_envelope(&FRAMES, 1, 4, &[0, 255])
+            .is_ok());
+
+        assert_eq!(array.frame_count, 4);
+        let (dark, lit) = array.spi.written.split_at(128);
+        assert!(dark.iter().all(|&b| b == 0xff));
+        assert!(lit.iter().all(|&b| b == 0x00));
+        assert_eq!(array.brightness, 255);
+    }
+
+    #[test]
+    fn test_blend() {
+        let mut overlay = Frame([[0; NUM_COLS]; NUM_ROWS]);
+        overlay.0[0][..4].copy_from_slice(&[0, 3, 9, 12]);
+        let base = [[6; NUM_COLS]; NUM_ROWS];
+
+        let cases = [
+            (BlendMode::Max, [6, 6, 9, 12]),
+            (BlendMode::Add, [6, 9, 15, 15]),
+            (BlendMode::Min, [0, 3, 6, 6]),
+            (BlendMode::Over, [6, 3, 9, 12]),
+        ];
+        for &(mode, expected) in cases.iter() {
+            let mut array = mock_array();
+            array.array = base;
+            array.blend(&overlay, mode);
+            assert_eq!(array.array[0][..4], expected, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn test_blend_masked() {
+        let overlay = Frame([[0; NUM_COLS]; NUM_ROWS]);
+        let mut alpha = [[false; NUM_COLS]; NUM_ROWS];
+        alpha[2][3] = true;
+
+        let mut array = mock_array();
+        array.array = [[6; NUM_COLS]; NUM_ROWS];
+        array.blend_masked(&overlay, &alpha, BlendMode::Min);
+
+        // only the opaque cell takes the overlay's zero
+        assert_eq!(array.array[2][3], 0);
+        let untouched = array.array.iter().flatten().filter(|&&b| b == 6).count();
+        assert_eq!(untouched, NUM_ROWS * NUM_COLS - 1);
+    }
+
+    #[test]
+    fn test_wipe_to() {
+        let target = Frame([[9; NUM_COLS]; NUM_ROWS]);
+        let mut array = mock_array();
+
+        assert!(!array.wipe_to(&target, Direction::Horizontal, 6));
+        assert!(array
+            .array
+            .iter()
+            .all(|row| row[..6] == [9; 6] && row[6..] == [0; 10]));
+
+        assert!(!array.wipe_to(&target, Direction::Horizontal, 6));
+        assert!(array
+            .array
+            .iter()
+            .all(|row| row[..12] == [9; 12] && row[12..] == [0; 4]));
+
+        assert!(array.wipe_to(&target, Direction::Horizontal, 6));
+        assert_eq!(array.array, target.0);
+
+        // the next wipe starts over
+        let target = Frame([[2; NUM_COLS]; NUM_ROWS]);
+        assert!(!array.wipe_to(&target, Direction::Vertical, 4));
+        assert_eq!(array.array[3], [2; NUM_COLS]);
+        assert_eq!(array.array[4], [9; NUM_COLS]);
+        assert!(array.wipe_to(&target, Direction::Vertical, 4));
+        assert_eq!(array.array, target.0);
+
+        // a huge step partway through finishes without overflowing
+        let target = Frame([[5; NUM_COLS]; NUM_ROWS]);
+        assert!(!array.wipe_to(&target, Direction::Horizontal, 1));
+        assert!(array.wipe_to(&target, Direction::Horizontal, usize::MAX));
+        assert_eq!(array.array, target.0);
+
+        // and a step of 0 still gets there
+        let target = Frame([[1; NUM_COLS]; NUM_ROWS]);
+        assert!(!array.wipe_to(&target, Direction::Vertical, 0));
+        assert_eq!(array.array[0], [1; NUM_COLS]);
+        assert_eq!(array.array[1], [5; NUM_COLS]);
+        let frames = (0..NUM_ROWS).find(|_| array.wipe_to(&target, Direction::Vertical, 0));
+        assert_eq!(frames, Some(NUM_ROWS - 2));
+        assert_eq!(array.array, target.0);
+    }
+
+    #[test]
+    fn test_crc8() {
+        // the standard check value for CRC-8/SMBUS
+        assert_eq!(crc8(b"123456789"), 0xf4);
+    }
+
+    #[test]
+    fn test_frame_checked_round_trip() {
+        let mut sender = mock_array();
+        sender.array[0][0] = 1;
+        sender.array[4][9] = 13;
+        let mut bytes = [0u8; CHECKED_FRAME_LEN];
+        sender.dump_frame_checked(&mut bytes);
+
+        let mut receiver = mock_array();
+        assert_eq!(receiver.load_frame_checked(&bytes), Ok(()));
+        assert_eq!(receiver.array, sender.array);
+    }
+
+    #[test]
+    fn test_frame_checked_corruption() {
+        let mut sender = mock_array();
+        sender.array[2][2] = 6;
+        let mut bytes = [0u8; CHECKED_FRAME_LEN];
+        sender.dump_frame_checked(&mut bytes);
+        let crc = bytes[CHECKED_FRAME_LEN - 1];
+
+        let mut receiver = mock_array();
+        bytes[5] ^= 0x04;
+        assert!(matches!(
+            receiver.load_frame_checked(&bytes),
+            Err(ChecksumError::Mismatch { got, .. }) if got == crc
+        ));
+        assert!(receiver.is_blank());
+
+        assert_eq!(
+            receiver.load_frame_checked(&bytes[1..]),
+            Err(ChecksumError::WrongLength(WrongLength {
+                expected: CHECKED_FRAME_LEN,
+                got: CHECKED_FRAME_LEN - 1,
+            }))
+        );
+        assert!(receiver.load_frame_checked(&[]).is_err());
+    }
+
+    #[test]
+    fn test_frame_constructors() {
+        assert!(Frame::zeroed().0.iter().flatten().all(|&b| b == 0));
+        assert!(Frame::filled(6).0.iter().flatten().all(|&b| b == 6));
+
+        const BOARD: Frame = Frame::checkerboard();
+        assert_eq!(BOARD.0[0][..4], [15, 0, 15, 0]);
+        assert_eq!(BOARD.0[1][..4], [0, 15, 0, 15]);
+        let lit = BOARD.0.iter().flatten().filter(|&&b| b == 15).count();
+        assert_eq!(lit, NUM_ROWS * NUM_COLS / 2);
+    }
+
+    #[test]
+    fn test_frame_try_from() {
+        let mut bytes = [0u8; NUM_ROWS * NUM_COLS];
+        bytes[NUM_COLS + 2] = 7;
+        bytes[NUM_ROWS * NUM_COLS - 1] = 3;
+
+        let frame = Frame::try_from(&bytes[..]).unwrap();
+        assert_eq!(frame.0[1][2], 7);
+        assert_eq!(frame.0[7][15], 3);
+
+        assert_eq!(
+            Frame::try_from(&bytes[1..]),
+            Err(WrongLength {
+                expected: 128,
+                got: 127
+            })
+        );
+    }
+}
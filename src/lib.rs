@@ -1,36 +1,397 @@
 #![no_std]
+// Firmware using this crate can't recover from a panic, so any failure is
+// surfaced as an error instead. Indexing is checked by hand where it happens.
+#![cfg_attr(
+    not(test),
+    deny(
+        clippy::panic,
+        clippy::unwrap_used,
+        clippy::expect_used,
+        clippy::unreachable,
+        clippy::todo,
+        clippy::unimplemented
+    )
+)]
+
+use core::convert::TryFrom;
 
 use embedded_hal as hal;
+use hal::blocking::spi::Write;
 use hal::digital::v2::OutputPin;
-use hal::spi::FullDuplex;
-use nb::block;
+
+mod breathe;
+mod color;
+mod cursor;
+mod diagnose;
+mod draw;
+pub mod fonts;
+mod frame;
+mod marquee;
+pub mod math;
+mod pixels;
+pub mod prelude;
+mod segment;
+#[cfg(feature = "critical-section")]
+mod shared;
+mod status;
+mod transform;
+
+pub use breathe::Breathe;
+pub use color::{luma, ColorOrder};
+pub use cursor::{Cursor, CursorWriter};
+pub use draw::{Direction, GrayImage};
+pub use frame::{
+    BlendMode, ChecksumError, Frame, FrameSource, PlayError, WrongLength, CHECKED_FRAME_LEN,
+};
+pub use marquee::Marquee;
+pub use pixels::Pixels;
+#[cfg(feature = "critical-section")]
+pub use shared::SharedArray;
+pub use status::StatusLine;
+pub use transform::{Rotation, Transform};
 
 const ROW_BITS: usize = 3;
-const COL_BITS: usize = 4;
 const LAYER_BITS: usize = 4;
 
 const NUM_ROWS: usize = 1 << ROW_BITS;
-const NUM_COLS: usize = 1 << COL_BITS;
-const SPI_BYTES: usize = NUM_COLS / 8;
+// any count works, not just multiples of 8
+const NUM_COLS: usize = 16;
+// rounded up to whole bytes, with any spare bits off at the far end of the chain
+const SPI_BYTES: usize = NUM_COLS.div_ceil(8);
+
+/// The SPI mode the 74HC595 style shift registers expect: data is sampled on
+/// the rising clock edge with the clock idling low. The driver never
+/// configures the bus itself, so set this up when creating `spi`.
+pub const EXPECTED_SPI_MODE: hal::spi::Mode = hal::spi::MODE_0;
+
+/// The frame rate above which most people stop seeing a panel flicker.
+pub const FLICKER_FREE_HZ: u32 = 100;
+
+/// The size of the buffer filled by `prepare_dma_buffer`.
+pub const DMA_BUFFER_LEN: usize = NUM_ROWS * LAYER_BITS * SPI_BYTES;
+
+/// How many pixels `blink_pixel` can keep blinking at once.
+pub const MAX_BLINKERS: usize = 8;
 
 pub struct LEDArray<R0, R1, R2, Timer, SPI, Reg, OD> {
     pub array: [[u8; NUM_COLS]; NUM_ROWS],
 
+    /// Shown on top of `array` wherever it isn't off, for things like a cursor
+    /// or status icon that shouldn't be drawn into the content itself.
+    /// Defaults to `None`.
+    pub overlay: Option<Frame>,
+
     pub row_pins: (R0, R1, R2),
 
     pub timer: Timer,
 
+    /// Any blocking SPI writer works, as the shift registers are never read.
+    /// A `FullDuplex` driver can be used by implementing
+    /// `embedded_hal::blocking::spi::write::Default` for it.
     pub spi: SPI,
     pub reg_pin: Reg,
     pub output_disable: OD,
+
+    /// Called at the start of every `scan`, before any rows are written.
+    pub on_frame: Option<fn()>,
+
+    /// The number of frames scanned so far.
+    pub frame_count: u32,
+
+    // bumped by every drawing method, see `generation`
+    generation: u32,
+
+    /// The order `scan` visits rows in. Every entry must be below `NUM_ROWS`.
+    pub row_order: [usize; NUM_ROWS],
+
+    /// Visit `row_order` backwards on every odd frame, so the rolling edge
+    /// of the scan sweeps up and down instead of always tearing moving
+    /// content in the same direction. Defaults to `false`.
+    pub reverse_scan_each_frame: bool,
+
+    /// Rotate the order each row's layers go out in, so neighbouring rows
+    /// show their long, bright layers at different times in the row period.
+    /// The total current drawn then stays flatter, which means less ripple
+    /// on the supply. Each layer still lasts as long as its weight, so
+    /// brightness is unchanged. `step` ignores this. Defaults to `false`.
+    pub stagger_layers: bool,
+
+    /// Scales every pixel, from 0 for off to 255 for full brightness.
+    pub brightness: u8,
+
+    /// Leave `brightness` to a hardware PWM channel driving the output enable
+    /// line, set up with `update_oe_pwm`, rather than scaling the pixels by
+    /// it. Defaults to `false`.
+    pub pwm_brightness: bool,
+
+    /// Scales each row on top of `brightness`, from 0 for off to 255 for full.
+    pub row_brightness: [u8; NUM_ROWS],
+
+    /// Per LED correction for panels whose LEDs don't match, scaling each
+    /// pixel by its entry out of 255 on top of `brightness`.
+    pub calibration: Option<&'static [[u8; NUM_COLS]; NUM_ROWS]>,
+
+    /// Which cells have an LED, for panels that don't fill the whole matrix,
+    /// such as a ring of LEDs. Cells marked `false` are always sent as off,
+    /// so drawing into them is harmless. `None`, the default, has every cell.
+    pub shape_mask: Option<&'static [[bool; NUM_COLS]; NUM_ROWS]>,
+
+    /// The level actually sent for each of the 16 brightness levels, after
+    /// `brightness` and `calibration` are applied. Driver turn on and off
+    /// delays eat into the short layers, making the low levels dimmer than
+    /// their share of the frame, so a panel that has been measured can bump
+    /// those up here. Defaults to sending every level as is.
+    pub timing_correction: [u8; 16],
+
+    /// Skip scanning frames where every pixel is off, turning the outputs off
+    /// instead. Defaults to `false`.
+    pub skip_blank: bool,
+
+    /// Only shift out layers that differ from what was last sent for the same
+    /// row and layer. The rest aren't shifted or latched, though rows are
+    /// still switched and every layer still gets its time. This is only for
+    /// drivers that keep every row's data themselves; on a plain multiplexed
+    /// panel the skipped rows would go dark. Writing to the driver any other
+    /// way, such as with `write_layer` or `reset_registers`, makes the next
+    /// scan send everything. Defaults to `false`.
+    pub skip_unchanged: bool,
+
+    /// Don't shift out layers with every pixel off. The output is blanked for
+    /// the layer's time instead, which looks the same, so sparse or dim
+    /// content spends less time on SPI. Defaults to `false`.
+    pub skip_empty_layers: bool,
+
+    // the layers last sent for each row, used by `skip_unchanged`
+    sent: [[Option<[u8; SPI_BYTES]>; LAYER_BITS]; NUM_ROWS],
+
+    /// Latch a new row's data before blanking the output to switch rows, so
+    /// the output is only off while the row pins change.
+    ///
+    /// Normally the latch happens inside the blank window too. On a typical
+    /// MCU a GPIO write takes a few cycles while the row pins take three, so
+    /// this cuts the dark time per row roughly by a quarter, which shows up
+    /// as a little more brightness at high refresh rates. The catch is that
+    /// the new data briefly shows on the previous row, which can ghost on
+    /// slow pins. Defaults to `false`.
+    pub fast_row_switch: bool,
+
+    /// How many extra times to drive `reg_pin` high after raising it, holding
+    /// the latch pulse for registers that miss one as short as a single GPIO
+    /// write. Each write takes a fixed number of bus cycles and can't be
+    /// optimized away, unlike an empty loop. Defaults to 0.
+    pub latch_pulse_ticks: u32,
+
+    // whether we left `output_disable` high
+    blanked: bool,
+
+    // the bytes `write_layer` last shifted out, for `keepalive`
+    last_sent: Option<[u8; SPI_BYTES]>,
+
+    // how many of the most significant layers to show, set by `set_bit_depth`
+    bit_depth: usize,
+
+    /// Whether the column drivers are active low, so each layer is inverted
+    /// before it is shifted out. Defaults to `true`.
+    pub invert_data: bool,
+
+    /// The most bytes sent in one SPI write, for peripherals that can't take a
+    /// whole layer at once. Defaults to a whole layer, as does 0.
+    pub spi_chunk: usize,
+
+    /// How many times to poll the timer for the end of a layer before giving
+    /// up with `LEDError::TimerStuck`. `None`, the default, waits forever.
+    pub max_timer_spins: Option<u32>,
+
+    /// How many times to retry a non-blocking SPI call that returns
+    /// `WouldBlock` before giving up with `LEDError::SpiTimeout`. Only the
+    /// `FullDuplex` transfers in `diagnose` use this, as the blocking writes
+    /// every scan makes can't report `WouldBlock`. `None`, the default,
+    /// retries forever.
+    pub max_spi_spins: Option<u32>,
+
+    // set by `set_base_freq`, used by `refresh`
+    base_freq: Option<u32>,
+
+    // for `refresh_compensated`: when the last layer started and how many
+    // ticks it was meant to last, and the ticks still to be made up
+    last_layer_start: Option<(u32, u32)>,
+    timing_debt: u32,
+
+    // set at the end of every frame, cleared by `take_frame_complete`
+    frame_complete: bool,
+
+    // set by `pause`, stops animations advancing
+    paused: bool,
+
+    // set by `set_inverted`
+    inverted: bool,
+
+    // set by `freeze`, shown instead of `array`
+    frozen: Option<[[u8; NUM_COLS]; NUM_ROWS]>,
+
+    // set by `request_swap`, copies `array` into `frozen` at the next frame
+    swap_pending: bool,
+
+    /// How drawing coordinates map onto `array`, applied by `set_pixel`,
+    /// `draw_iter` and everything drawn through them. `array` holds the
+    /// pixels as wired, so scanning shifts it out as it is. Whole panel
+    /// operations like `load_frame` bypass it. Defaults to no change.
+    pub transform: Transform,
+
+    // set by `set_clip`: the left, top, right and bottom edges, the last two
+    // exclusive
+    clip: Option<(i32, i32, i32, i32)>,
+
+    // how many columns or rows `wipe_to` has revealed
+    wipe_progress: usize,
+
+    // set by `set_alert`: the cells that flash, the frames per half flash and
+    // the frame it started on
+    alert: Option<([[bool; NUM_COLS]; NUM_ROWS], u32, u32)>,
+
+    // the pixels set blinking by `blink_pixel`
+    blinkers: heapless::Vec<Blinker, MAX_BLINKERS>,
+
+    // the last frame `apply_smoothing` produced
+    smoothed: [[u8; NUM_COLS]; NUM_ROWS],
+
+    // where `step` is up to: an index into `row_order`, the next layer, and
+    // the layers of the row being shown
+    step_row: usize,
+    step_layer: usize,
+    step_layers: [[u8; SPI_BYTES]; LAYER_BITS],
+
+    // the row `scan_group` is showing on this panel, and its layers
+    group_row: usize,
+    group_layers: [[u8; SPI_BYTES]; LAYER_BITS],
 }
 
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum LEDError<P, S> {
     PinError(P),
     SPIError(S),
+    /// The layer passed to `write_layer` was not `SPI_BYTES` long.
+    LengthMismatch {
+        expected: usize,
+        got: usize,
+    },
+    /// A row at or past `NUM_ROWS` was asked for.
+    InvalidRow(usize),
+    /// The timer was polled `max_timer_spins` times without expiring.
+    TimerStuck,
+    /// `refresh` was called before `set_base_freq`.
+    NoBaseFreq,
+    /// A non-blocking SPI call returned `WouldBlock` more than
+    /// `max_spi_spins` times in a row.
+    SpiTimeout,
+    /// `write_layer_segmented` was given a number of latches that doesn't
+    /// split `SPI_BYTES` evenly, including none at all.
+    LatchCount(usize),
+}
+
+impl<P, S> LEDError<P, S> {
+    /// A number for each kind of error, for reporting over FFI or a wire
+    /// where the pin and SPI error types can't go. Codes never change once
+    /// given out, and 0 is left free to mean success.
+    pub fn code(&self) -> u8 {
+        match self {
+            LEDError::PinError(_) => 1,
+            LEDError::SPIError(_) => 2,
+            LEDError::LengthMismatch { .. } => 3,
+            LEDError::InvalidRow(_) => 4,
+            LEDError::TimerStuck => 5,
+            LEDError::NoBaseFreq => 6,
+            LEDError::SpiTimeout => 7,
+            LEDError::LatchCount(_) => 8,
+        }
+    }
+}
+
+/// A timer that can say how long it has left to run, for `try_step`.
+///
+/// embedded-hal's `CountDown` can only be asked whether it has expired. HAL
+/// timers that can also read their counter can implement this to let the
+/// scan be driven without blocking.
+pub trait Remaining: hal::timer::CountDown {
+    /// Ticks until the timer expires, 0 once it has.
+    fn remaining(&self) -> u32;
+}
+
+/// A row at or past `NUM_ROWS` was asked for.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InvalidRow(pub usize);
+
+impl<P, S> From<InvalidRow> for LEDError<P, S> {
+    fn from(InvalidRow(row): InvalidRow) -> Self {
+        LEDError::InvalidRow(row)
+    }
 }
 
 impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    pub fn new(
+        row_pins: (R0, R1, R2),
+        timer: Timer,
+        spi: SPI,
+        reg_pin: Reg,
+        output_disable: OD,
+    ) -> Self {
+        LEDArray {
+            array: [[0; NUM_COLS]; NUM_ROWS],
+            overlay: None,
+            row_pins,
+            timer,
+            spi,
+            reg_pin,
+            output_disable,
+            on_frame: None,
+            frame_count: 0,
+            generation: 0,
+            row_order: core::array::from_fn(|row| row),
+            reverse_scan_each_frame: false,
+            stagger_layers: false,
+            brightness: 255,
+            pwm_brightness: false,
+            row_brightness: [255; NUM_ROWS],
+            calibration: None,
+            shape_mask: None,
+            timing_correction: [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+            skip_blank: false,
+            skip_unchanged: false,
+            skip_empty_layers: false,
+            sent: [[None; LAYER_BITS]; NUM_ROWS],
+            fast_row_switch: false,
+            latch_pulse_ticks: 0,
+            blanked: false,
+            last_sent: None,
+            bit_depth: LAYER_BITS,
+            invert_data: true,
+            spi_chunk: SPI_BYTES,
+            max_timer_spins: None,
+            max_spi_spins: None,
+            base_freq: None,
+            last_layer_start: None,
+            timing_debt: 0,
+            frame_complete: false,
+            paused: false,
+            inverted: false,
+            frozen: None,
+            swap_pending: false,
+            transform: Transform::default(),
+            clip: None,
+            wipe_progress: 0,
+            alert: None,
+            blinkers: heapless::Vec::new(),
+            smoothed: [[0; NUM_COLS]; NUM_ROWS],
+            step_row: 0,
+            step_layer: 0,
+            step_layers: [[0; SPI_BYTES]; LAYER_BITS],
+            group_row: 0,
+            group_layers: [[0; SPI_BYTES]; LAYER_BITS],
+        }
+    }
+
     fn write_row<PinError>(&mut self, row: usize) -> Result<(), PinError>
     where
         R0: OutputPin<Error = PinError>,
@@ -49,12 +410,140 @@ impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>
             }
         }
 
-        set_pin(&mut self.row_pins.0, ((row >> 0) & 1) == 1)?;
+        set_pin(&mut self.row_pins.0, (row & 1) == 1)?;
         set_pin(&mut self.row_pins.1, ((row >> 1) & 1) == 1)?;
         set_pin(&mut self.row_pins.2, ((row >> 2) & 1) == 1)?;
         Ok(())
     }
 
+    /// Drive `output_disable` high to blank the panel, unless this driver
+    /// already left it high. Call it before anything that changes the row
+    /// addressing so a half switched row can't ghost.
+    ///
+    /// Only changes made through the driver are tracked, so if you drive
+    /// `output_disable` directly, call `unblank` rather than lowering it
+    /// yourself.
+    pub fn ensure_blanked(&mut self) -> Result<(), OD::Error>
+    where
+        OD: OutputPin,
+    {
+        if !self.blanked {
+            self.output_disable.set_high()?;
+            self.blanked = true;
+        }
+        Ok(())
+    }
+
+    /// Drive `output_disable` low to show the latched row again.
+    pub fn unblank(&mut self) -> Result<(), OD::Error>
+    where
+        OD: OutputPin,
+    {
+        self.output_disable.set_low()?;
+        self.blanked = false;
+        Ok(())
+    }
+
+    /// Drive a board's "all rows" enable line, turning every row on at once
+    /// regardless of the row address pins, which are left untouched.
+    ///
+    /// **Warning:** this lights up to `NUM_ROWS` times as many LEDs as normal
+    /// scanning does, and draws that much more current. Only use it on
+    /// hardware with a supply and drivers rated for the whole panel being lit,
+    /// and only for as long as the test needs. Data is still shifted in with
+    /// `write_layer(layer, None)`.
+    pub fn all_rows_on<E>(&mut self, enable: &mut E) -> Result<(), E::Error>
+    where
+        E: OutputPin,
+    {
+        enable.set_high()
+    }
+
+    /// Release the "all rows" enable line, returning to normal scanning.
+    pub fn all_rows_off<E>(&mut self, enable: &mut E) -> Result<(), E::Error>
+    where
+        E: OutputPin,
+    {
+        enable.set_low()
+    }
+
+    /// Shift every column off into the registers and latch them, without
+    /// touching the row pins or `output_disable`.
+    pub fn reset_registers<PinError>(&mut self) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+    {
+        self.forget_sent();
+        self.reg_pin.set_low().map_err(LEDError::PinError)?;
+        let off = if self.invert_data { !0 } else { 0 };
+        self.send(&[off; SPI_BYTES]).map_err(LEDError::SPIError)?;
+        self.latch().map_err(LEDError::PinError)?;
+        Ok(())
+    }
+
+    /// Raise `reg_pin` to latch the shift registers, holding it for
+    /// `latch_pulse_ticks`.
+    fn latch(&mut self) -> Result<(), Reg::Error>
+    where
+        Reg: OutputPin,
+    {
+        pulse(&mut self.reg_pin, self.latch_pulse_ticks)
+    }
+
+    /// Shift the last layer written back into the registers and latch it
+    /// again, for driver chips that blank themselves if they aren't written
+    /// to often enough. Nothing shown changes, and neither the row pins nor
+    /// the timer are touched. Before any layer has been written the registers
+    /// are reset instead.
+    pub fn keepalive<PinError>(&mut self) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+    {
+        let data = match self.last_sent {
+            Some(data) => data,
+            None => return self.reset_registers(),
+        };
+        // which row and layer this came from isn't known any more
+        self.forget_sent();
+        self.reg_pin.set_low().map_err(LEDError::PinError)?;
+        self.send(&data).map_err(LEDError::SPIError)?;
+        self.latch().map_err(LEDError::PinError)?;
+        Ok(())
+    }
+
+    /// Write `data` to the bus, `spi_chunk` bytes at a time.
+    fn send(&mut self, data: &[u8]) -> Result<(), SPI::Error>
+    where
+        SPI: Write<u8>,
+    {
+        let chunk = if self.spi_chunk == 0 {
+            data.len().max(1)
+        } else {
+            self.spi_chunk
+        };
+        for piece in data.chunks(chunk) {
+            self.spi.write(piece)?;
+        }
+        Ok(())
+    }
+
+    /// Spin until the timer fires, giving up after `max_timer_spins`.
+    fn wait_for_timer<PinError>(&mut self) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        Timer: hal::timer::CountDown,
+        SPI: Write<u8>,
+    {
+        wait_bounded(&mut self.timer, self.max_timer_spins)
+    }
+
+    /// Shift out `layer`, one byte per 8 columns, and latch it once the
+    /// previous layer's time is up, switching to `row` first if given.
+    ///
+    /// A `layer` of any other length, including an empty one, is rejected
+    /// with `LEDError::LengthMismatch` before anything is sent or latched, so
+    /// the registers can't latch stale data in its place.
     pub fn write_layer<PinError>(
         &mut self,
         layer: &[u8],
@@ -65,43 +554,209 @@ impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>
         R1: OutputPin<Error = PinError>,
         R2: OutputPin<Error = PinError>,
         Timer: hal::timer::CountDown,
-        SPI: FullDuplex<u8>,
+        SPI: Write<u8>,
         Reg: OutputPin<Error = PinError>,
         OD: OutputPin<Error = PinError>,
     {
+        // the caller knows which row and layer this is, but the cache doesn't
+        self.forget_sent();
+        self.shift_layer(layer)?;
+
+        // wait for the previous layer's time to end
+        self.wait_for_timer()?;
+
+        self.show_layer(row)
+    }
+
+    /// `write_layer` for layer `layer` of `row`, noting it as what the driver
+    /// holds for that row and layer, for `skip_unchanged`.
+    fn write_row_layer<PinError>(
+        &mut self,
+        data: &[u8; SPI_BYTES],
+        row: usize,
+        layer: usize,
+        switch_row: bool,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        self.shift_layer(data)?;
+        self.note_sent(row, layer, data);
+
+        // wait for the previous layer's time to end
+        self.wait_for_timer()?;
+
+        self.show_layer(if switch_row { Some(row) } else { None })
+    }
+
+    /// Remember `data` as what was last shifted out for `row` and `layer`.
+    fn note_sent(&mut self, row: usize, layer: usize, data: &[u8; SPI_BYTES]) {
+        if let Some(sent) = self.sent.get_mut(row).and_then(|r| r.get_mut(layer)) {
+            *sent = Some(*data);
+        }
+    }
+
+    /// Forget what was sent for every row and layer, after the driver was
+    /// written to some other way, so `skip_unchanged` sends everything again.
+    fn forget_sent(&mut self) {
+        self.sent = [[None; LAYER_BITS]; NUM_ROWS];
+    }
+
+    /// The first half of `write_layer`: shift out `layer` without latching it.
+    fn shift_layer<PinError>(&mut self, layer: &[u8]) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+    {
+        let data = self.encode_layer(layer)?;
+
         // prepare to latch the shift registers
         self.reg_pin.set_low().map_err(LEDError::PinError)?;
 
         // write the shift register data
-        for &data in layer {
-            block!(self.spi.send(!data)).map_err(LEDError::SPIError)?;
+        self.send(&data).map_err(LEDError::SPIError)?;
+        self.last_sent = Some(data);
+        Ok(())
+    }
+
+    /// `layer` as it goes down the wire, inverted if `invert_data` is set, or
+    /// `LEDError::LengthMismatch` if it isn't `SPI_BYTES` long.
+    fn encode_layer<PinError>(
+        &self,
+        layer: &[u8],
+    ) -> Result<[u8; SPI_BYTES], LEDError<PinError, SPI::Error>>
+    where
+        SPI: Write<u8>,
+    {
+        if layer.len() != SPI_BYTES {
+            return Err(LEDError::LengthMismatch {
+                expected: SPI_BYTES,
+                got: layer.len(),
+            });
         }
 
-        // wait for the previous layer's time to end
-        block!(self.timer.wait()).unwrap(); // Err is Void
+        let mut data = [0u8; SPI_BYTES];
+        for (out, &byte) in data.iter_mut().zip(layer) {
+            *out = if self.invert_data { !byte } else { byte };
+        }
+        Ok(data)
+    }
 
+    /// The second half of `write_layer`: latch the shifted layer, switching to
+    /// `row` first if given.
+    fn show_layer<PinError>(
+        &mut self,
+        row: Option<usize>,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
         match row {
             // we aren't changing rows, so just latch the shift registers
-            None => self.reg_pin.set_high().map_err(LEDError::PinError)?,
+            None => self.latch().map_err(LEDError::PinError)?,
+
+            // we are switching rows, latching before blanking so only the row
+            // pins change while the output is off
+            Some(row) if self.fast_row_switch => {
+                self.latch().map_err(LEDError::PinError)?;
+
+                self.ensure_blanked().map_err(LEDError::PinError)?;
+                self.write_row(row).map_err(LEDError::PinError)?;
+                self.unblank().map_err(LEDError::PinError)?;
+            }
 
             // we are switching rows
             Some(row) => {
                 // disabel the columns while we are writing to the row pins
-                self.output_disable.set_high().map_err(LEDError::PinError)?;
+                self.ensure_blanked().map_err(LEDError::PinError)?;
                 // update the row pins
                 self.write_row(row).map_err(LEDError::PinError)?;
 
                 // latch the shift registers
-                self.reg_pin.set_high().map_err(LEDError::PinError)?;
+                self.latch().map_err(LEDError::PinError)?;
 
                 // enable the correct row
-                self.output_disable.set_low().map_err(LEDError::PinError)?;
+                self.unblank().map_err(LEDError::PinError)?;
             }
         };
 
         Ok(())
     }
 
+    /// `write_layer` for a panel split into segments that share the data line
+    /// but each have their own latch, given in `latches` instead of using
+    /// `reg_pin`.
+    ///
+    /// `layer` is split into one equal piece per latch, the first piece going
+    /// to `latches[0]`, and each latch is held for `latch_pulse_ticks` like
+    /// `reg_pin`. A number of latches that doesn't divide `SPI_BYTES` is
+    /// rejected with `LEDError::LatchCount` before anything is sent.
+    ///
+    /// The first piece is shifted out before the wait, as `write_layer` does.
+    /// Every segment sees the data meant for the others, so each later piece
+    /// can only be shifted once the one before it is latched, and the time
+    /// that takes comes out of the start of the layer.
+    pub fn write_layer_segmented<PinError, L>(
+        &mut self,
+        layer: &[u8],
+        row: Option<usize>,
+        latches: &mut [L],
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        SPI: Write<u8>,
+        OD: OutputPin<Error = PinError>,
+        L: OutputPin<Error = PinError>,
+    {
+        let data = self.encode_layer(layer)?;
+        let count = latches.len();
+        let (first_latch, other_latches) = match latches.split_first_mut() {
+            Some(split) if SPI_BYTES % count == 0 => split,
+            _ => return Err(LEDError::LatchCount(count)),
+        };
+        let (first, others) = data.split_at(SPI_BYTES / count);
+        self.forget_sent();
+
+        first_latch.set_low().map_err(LEDError::PinError)?;
+        self.send(first).map_err(LEDError::SPIError)?;
+
+        // wait for the previous layer's time to end
+        self.wait_for_timer()?;
+
+        if let Some(row) = row {
+            self.ensure_blanked().map_err(LEDError::PinError)?;
+            self.write_row(row).map_err(LEDError::PinError)?;
+        }
+
+        pulse(first_latch, self.latch_pulse_ticks).map_err(LEDError::PinError)?;
+        for (bytes, latch) in others.chunks(first.len()).zip(other_latches.iter_mut()) {
+            latch.set_low().map_err(LEDError::PinError)?;
+            self.send(bytes).map_err(LEDError::SPIError)?;
+            pulse(latch, self.latch_pulse_ticks).map_err(LEDError::PinError)?;
+        }
+        self.last_sent = Some(data);
+
+        if row.is_some() {
+            self.unblank().map_err(LEDError::PinError)?;
+        }
+
+        Ok(())
+    }
+
     pub fn scan<T, PinError>(&mut self, base_freq: T) -> Result<(), LEDError<PinError, SPI::Error>>
     where
         R0: OutputPin<Error = PinError>,
@@ -109,105 +764,2721 @@ impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>
         R2: OutputPin<Error = PinError>,
         Timer: hal::timer::CountDown,
         T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
-        SPI: FullDuplex<u8>,
+        SPI: Write<u8>,
         Reg: OutputPin<Error = PinError>,
         OD: OutputPin<Error = PinError>,
     {
+        let order = self.frame_order();
         let mut layers = [[0u8; SPI_BYTES]; LAYER_BITS];
+        self.scan_rows(order, &mut layers, weighted(base_freq), || Ok(()))
+    }
 
-        for row in 0..NUM_ROWS {
-            self.prepare_row(row, &mut layers);
+    /// Scan every panel in `panels` once, in lockstep off the one shared
+    /// `timer`, for panels that have to refresh together. The panels' own
+    /// timers aren't used, so they can be anything, such as `()`.
+    ///
+    /// Each layer is shifted out to every panel, then all of them are latched
+    /// together once the previous layer's time is up. Every panel shows all
+    /// `LAYER_BITS` layers in its own row order; `set_bit_depth`,
+    /// `skip_blank`, `skip_unchanged`, `skip_empty_layers` and
+    /// `stagger_layers` are ignored, since they would pull the panels out of
+    /// step. The wait for `timer` gives up with `LEDError::TimerStuck` after
+    /// the smallest `max_timer_spins` set on any of the panels.
+    pub fn scan_group<Shared, T, PinError>(
+        panels: &mut [Self],
+        timer: &mut Shared,
+        base_freq: T,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Shared: hal::timer::CountDown,
+        T: Into<Shared::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        for panel in panels.iter_mut() {
+            panel.start_frame();
+        }
+        let max_spins = panels
+            .iter()
+            .filter_map(|panel| panel.max_timer_spins)
+            .min();
+
+        for i in 0..NUM_ROWS {
+            for panel in panels.iter_mut() {
+                panel.group_row = panel.frame_order()[i];
+                let mut layers = panel.group_layers;
+                panel.prepare_row(panel.group_row, &mut layers)?;
+                panel.group_layers = layers;
+            }
 
             for layer in 0..LAYER_BITS {
-                self.write_layer(&layers[layer], if layer == 0 { Some(row) } else { None })?;
+                for panel in panels.iter_mut() {
+                    let data = panel.group_layers[layer];
+                    panel.shift_layer(&data)?;
+                    panel.note_sent(panel.group_row, layer, &data);
+                }
+
+                // wait for the previous layer's time to end
+                wait_bounded(timer, max_spins)?;
+
+                for panel in panels.iter_mut() {
+                    let row = if layer == 0 {
+                        Some(panel.group_row)
+                    } else {
+                        None
+                    };
+                    panel.show_layer(row)?;
+                }
 
                 // set the timer for this layer
-                let freq = base_freq << (LAYER_BITS - layer - 1);
-                self.timer.start(freq);
+                timer.start(base_freq << (LAYER_BITS - layer - 1));
             }
         }
 
+        for panel in panels.iter_mut() {
+            panel.end_frame();
+        }
         Ok(())
     }
 
-    pub fn prepare_row(&self, r: usize, buf: &mut [[u8; SPI_BYTES]; LAYER_BITS]) {
-        assert!(r < NUM_ROWS); // Maybe return an error somehow?
-        let row = self.array[r];
+    /// Scan with one layer per entry in `schedule`, layer `i` showing bit `i`
+    /// of each pixel's brightness for `schedule[i]`, instead of the usual
+    /// `LAYER_BITS` binary weighted layers. This is for experimenting with
+    /// other modulation schemes; layers past the 8th are always off.
+    pub fn scan_custom_layers<T, PinError>(
+        &mut self,
+        schedule: &[T],
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        self.start_frame();
 
-        for layer in 0..buf.len() {
-            let mut output = 0u16;
-            for brightness in row.iter().rev() {
-                // grab brightness mod 2^layer
-                let tmp = brightness % (2 << layer);
-                // left shift output
-                output = output << 1;
-                // add to output
-                output += tmp as u16 >> layer;
+        for row in self.frame_order() {
+            let brightness = self.adjusted_row(row)?;
+            for (layer, &duration) in schedule.iter().enumerate() {
+                let mut data = [0u8; SPI_BYTES];
+                pack_layer(&brightness, layer, &mut data);
+                self.write_layer(&data, if layer == 0 { Some(row) } else { None })?;
+                self.timer.start(duration);
             }
-
-            // update buffer[layer]
-            buf[layer] = output.to_be_bytes();
         }
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    mod mock;
-    use mock::*;
 
-    fn mock_array() -> LEDArray<MockPin, MockPin, MockPin, MockTimer, MockSPI, MockPin, MockPin> {
-        LEDArray {
-            array: [[0; 16]; 8],
-
-            row_pins: (MockPin::new(), MockPin::new(), MockPin::new()),
-
-            timer: MockTimer { tries: 0 },
-
-            spi: MockSPI {
-                written: heapless::Vec::new(),
-            },
-            reg_pin: MockPin::new(),
-            output_disable: MockPin::new(),
-        }
+        self.end_frame();
+        Ok(())
     }
 
-    #[test]
-    fn test_prepare_row() {
-        let row = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0];
-
-        let mut array = mock_array();
-        array.array = [row; 8];
+    /// Set the base frequency `refresh` scans at. It can be changed at any
+    /// time, e.g. to slow the refresh down while idle, and takes effect from
+    /// the next frame.
+    pub fn set_base_freq(&mut self, base_freq: u32) {
+        self.base_freq = Some(base_freq);
+    }
 
-        let mut buf = [[0u8; 2]; 4];
-        array.prepare_row(0, &mut buf);
+    pub fn base_freq(&self) -> Option<u32> {
+        self.base_freq
+    }
 
-        assert_eq!(buf[0], [0b01010101, 0b01010101]); // for 1s
-        assert_eq!(buf[1], [0b00110011, 0b00110011]); // for 2s
-        assert_eq!(buf[2], [0b00001111, 0b00001111]); // for 4s
-        assert_eq!(buf[3], [0b00000000, 0b11111111]); // for 8s
+    /// `scan` at the frequency given to `set_base_freq`, so it doesn't have
+    /// to be passed around to every call.
+    pub fn refresh<PinError>(&mut self) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        u32: Into<Timer::Time>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        let base_freq = self.base_freq.ok_or(LEDError::NoBaseFreq)?;
+        self.scan(base_freq)
     }
 
-    #[test]
-    fn test_write_layer() {
-        let mut array = mock_array();
+    /// `scan`, preparing each row in `scratch` instead of a buffer on the
+    /// stack, so it can live somewhere else such as a `static`. Its contents
+    /// going in don't matter and are left holding the last row written.
+    pub fn scan_with_scratch<T, PinError>(
+        &mut self,
+        base_freq: T,
+        scratch: &mut [[u8; SPI_BYTES]; LAYER_BITS],
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        let order = self.frame_order();
+        self.scan_rows(order, scratch, weighted(base_freq), || Ok(()))
+    }
+
+    /// `scan`, pulsing `trace` high then low at the start of the frame and
+    /// again just before each row is written, so a logic analyzer can line
+    /// the SPI traffic up with the scan. A frame shows up as `NUM_ROWS + 1`
+    /// pulses.
+    pub fn scan_traced<T, PinError, P>(
+        &mut self,
+        base_freq: T,
+        trace: &mut P,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+        P: OutputPin<Error = PinError>,
+    {
+        let mut pulse = || {
+            trace.set_high()?;
+            trace.set_low()
+        };
+
+        pulse().map_err(LEDError::PinError)?;
+        let order = self.frame_order();
+        let mut layers = [[0u8; SPI_BYTES]; LAYER_BITS];
+        self.scan_rows(order, &mut layers, weighted(base_freq), pulse)
+    }
+
+    /// Scan only the even rows on even frames and the odd rows on odd frames.
+    ///
+    /// This halves both the power draw and the SPI traffic per frame, at the
+    /// cost of halving the refresh rate of each row.
+    pub fn scan_interlaced<T, PinError>(
+        &mut self,
+        base_freq: T,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        let parity = (self.frame_count % 2) as usize;
+        let order = self.frame_order();
+        let mut layers = [[0u8; SPI_BYTES]; LAYER_BITS];
+        self.scan_rows(
+            order.iter().copied().filter(|row| row % 2 == parity),
+            &mut layers,
+            weighted(base_freq),
+            || Ok(()),
+        )
+    }
+
+    /// Scan `rows`, preparing each in `layers`. `start_layer` starts the
+    /// timer for each layer as it is latched, and `on_row` runs before each
+    /// row is written.
+    fn scan_rows<PinError, I, S, F>(
+        &mut self,
+        rows: I,
+        layers: &mut [[u8; SPI_BYTES]; LAYER_BITS],
+        mut start_layer: S,
+        mut on_row: F,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+        I: IntoIterator<Item = usize>,
+        S: FnMut(&mut Timer, usize),
+        F: FnMut() -> Result<(), PinError>,
+    {
+        self.start_frame();
+
+        if self.skip_blank && self.shows_nothing() {
+            // the last row shown is still latched, so turn it off too
+            self.ensure_blanked().map_err(LEDError::PinError)?;
+            // a blank frame still counts, so animations keep time
+            self.end_frame();
+            return Ok(());
+        }
+
+        let first = self.first_layer();
+
+        // whether the output was blanked for an empty layer since the last
+        // write
+        let mut skipped = false;
+
+        for row in rows {
+            self.prepare_row(row, layers)?;
+            on_row().map_err(LEDError::PinError)?;
+
+            // with `stagger_layers` each row starts one layer further round
+            let depth = LAYER_BITS - first;
+            let offset = if self.stagger_layers { row % depth } else { 0 };
+            for i in 0..depth {
+                let layer = first + (i + offset) % depth;
+                let data = &layers[layer];
+                let unchanged = self.skip_unchanged && self.sent[row][layer] == Some(*data);
+
+                if unchanged {
+                    // the driver still has this layer, so only the shift is
+                    // skipped; the row and timing carry on as normal
+                    self.wait_for_timer()?;
+                    if i == 0 {
+                        self.ensure_blanked().map_err(LEDError::PinError)?;
+                        self.write_row(row).map_err(LEDError::PinError)?;
+                    }
+                    // back on after the row switch, or after an empty layer
+                    if core::mem::take(&mut skipped) || i == 0 {
+                        self.unblank().map_err(LEDError::PinError)?;
+                    }
+                } else if self.skip_empty_layers && data.iter().all(|&byte| byte == 0) {
+                    self.wait_for_timer()?;
+                    self.ensure_blanked().map_err(LEDError::PinError)?;
+                    if i == 0 {
+                        self.write_row(row).map_err(LEDError::PinError)?;
+                    }
+                    skipped = true;
+                } else {
+                    let data = *data;
+                    self.write_row_layer(&data, row, layer, i == 0)?;
+                    if core::mem::take(&mut skipped) {
+                        self.unblank().map_err(LEDError::PinError)?;
+                    }
+                }
+
+                // set the timer for this layer
+                start_layer(&mut self.timer, layer);
+            }
+        }
+
+        self.end_frame();
+        Ok(())
+    }
+
+    /// Write the next single layer of the scan, for driving the panel from a
+    /// timer interrupt instead of a blocking loop. Returns `true` when this
+    /// call finished a frame.
+    ///
+    /// Call it each time the timer fires, so the wait in `write_layer` returns
+    /// straight away. A row is prepared from `array` when its first layer is
+    /// written, so changes made between calls show up from the next row on
+    /// and a row never mixes old and new pixels.
+    ///
+    /// When `step` runs in an interrupt and the main loop draws into `array`,
+    /// the two share the whole `LEDArray`. With the `critical-section`
+    /// feature, keep it in a [`SharedArray`], which takes the lock for each
+    /// call to `step` and for each batch of drawing. A `step`
+    /// only ever holds the lock for one layer's worth of SPI traffic, so the
+    /// main loop is never blocked for long, and keeping drawing batches short
+    /// keeps the interrupt from being delayed, which would show up as a
+    /// flicker in that layer.
+    pub fn step<T, PinError>(
+        &mut self,
+        base_freq: T,
+    ) -> Result<bool, LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        let row = *self
+            .frame_order()
+            .get(self.step_row)
+            .ok_or(InvalidRow(self.step_row))?;
+        let first = self.first_layer();
+        let layer = self.step_layer.max(first);
+
+        if layer == first {
+            if self.step_row == 0 {
+                self.start_frame();
+            }
+            let mut layers = self.step_layers;
+            self.prepare_row(row, &mut layers)?;
+            self.step_layers = layers;
+        }
+
+        let data = self.step_layers[layer];
+        self.write_row_layer(&data, row, layer, layer == first)?;
+
+        // set the timer for this layer
+        let freq = base_freq << (LAYER_BITS - layer - 1);
+        self.timer.start(freq);
+
+        self.step_layer = layer + 1;
+        if self.step_layer < LAYER_BITS {
+            return Ok(false);
+        }
+        self.step_layer = 0;
+        self.step_row += 1;
+        if self.step_row < NUM_ROWS {
+            return Ok(false);
+        }
+        self.step_row = 0;
+        self.end_frame();
+        Ok(true)
+    }
+
+    /// `step`, but only once the current layer's time is up, returning
+    /// `WouldBlock` without touching the hardware before then. This lets a
+    /// polling main loop do other work between layers rather than block in
+    /// `step`, on timers that can report what they have left; with any other
+    /// timer, use `step`.
+    pub fn try_step<T, PinError>(
+        &mut self,
+        base_freq: T,
+    ) -> nb::Result<bool, LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: Remaining,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        if self.ticks_until_step() > 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.step(base_freq).map_err(nb::Error::Other)
+    }
+
+    /// How many timer ticks are left before the next `step` is due, so a
+    /// caller can sleep or schedule other work for that long.
+    pub fn ticks_until_step(&self) -> u32
+    where
+        Timer: Remaining,
+    {
+        self.timer.remaining()
+    }
+
+    /// `step` up to `max_layers` times, stopping early at the end of a frame,
+    /// for cooperative schedulers that need to get back to something like a
+    /// watchdog before a whole `scan` could finish. The next call carries on
+    /// where this one stopped. Returns `true` if a frame was finished.
+    pub fn refresh_budget<T, PinError>(
+        &mut self,
+        base_freq: T,
+        max_layers: usize,
+    ) -> Result<bool, LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        for _ in 0..max_layers {
+            if self.step(base_freq)? {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Show only the `depth` most significant layers of each pixel, from 1 for
+    /// plain on/off up to `LAYER_BITS` for full grayscale. Fewer layers means
+    /// less SPI traffic per row, so a faster refresh. Out of range depths are
+    /// clamped.
+    pub fn set_bit_depth(&mut self, depth: u8) {
+        self.bit_depth = (depth as usize).clamp(1, LAYER_BITS);
+    }
+
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth as u8
+    }
+
+    /// How many bytes each layer of a row takes on the bus, for sizing
+    /// buffers like the one `prepare_row` fills.
+    pub const fn spi_bytes(&self) -> usize {
+        SPI_BYTES
+    }
+
+    /// Run `on_frame` and make any swap `request_swap` asked for.
+    fn start_frame(&mut self) {
+        if let Some(on_frame) = self.on_frame {
+            on_frame();
+        }
+        if core::mem::take(&mut self.swap_pending) {
+            self.frozen = Some(self.array);
+        }
+    }
+
+    /// A counter that goes up whenever a drawing method changes `array`, so
+    /// a caller can compare it with an earlier value to tell whether there is
+    /// anything new to show. It wraps around, so only compare for equality.
+    /// Writes straight to the `array` field aren't counted.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Note that `array` has changed.
+    fn touch(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Count a finished frame.
+    fn end_frame(&mut self) {
+        self.frame_count = self.frame_count.wrapping_add(1);
+        self.frame_complete = true;
+    }
+
+    /// Whether a frame has finished since the last call, clearing the flag.
+    ///
+    /// With `step` driven from an interrupt, the main loop can poll this to
+    /// know when to draw the next frame.
+    pub fn take_frame_complete(&mut self) -> bool {
+        core::mem::take(&mut self.frame_complete)
+    }
+
+    /// The order to visit rows in this frame.
+    fn frame_order(&self) -> [usize; NUM_ROWS] {
+        let mut order = self.row_order;
+        if self.reverse_scan_each_frame && self.frame_count % 2 == 1 {
+            order.reverse();
+        }
+        order
+    }
+
+    /// The least significant layer shown at the current bit depth.
+    fn first_layer(&self) -> usize {
+        LAYER_BITS - self.bit_depth
+    }
+
+    /// Whether every pixel in the array is off.
+    pub fn is_blank(&self) -> bool {
+        self.array
+            .iter()
+            .flatten()
+            .all(|&brightness| brightness == 0)
+    }
+
+    /// Freeze any animations drawing into this array, such as a `Marquee`, on
+    /// their current frame. Scanning carries on, so the frame stays shown.
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    /// Let paused animations carry on from where they stopped.
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Show every pixel's brightness flipped, off as full and full as off, for
+    /// dark text on a lit background. This is only how the image looks, and
+    /// is separate from `invert_data`, which is about how the column drivers
+    /// are wired.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+        self.forget_sent();
+    }
+
+    pub fn is_inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Keep showing the current contents of `array` until `unfreeze`, however
+    /// it is drawn into in the meantime. Unlike `pause` this stops the
+    /// display rather than the animations, which carry on drawing unseen.
+    pub fn freeze(&mut self) {
+        self.frozen = Some(self.array);
+    }
+
+    /// Go back to showing `array` as it is now.
+    pub fn unfreeze(&mut self) {
+        self.frozen = None;
+    }
+
+    pub fn is_frozen(&self) -> bool {
+        self.frozen.is_some()
+    }
+
+    /// Show the contents of `array` as they are at the start of the next
+    /// frame, then hold them as `freeze` does until the next swap.
+    ///
+    /// This double buffers the display, with `array` as the back buffer: draw
+    /// into it, call this once the drawing is done, and the whole new frame
+    /// appears at a frame boundary rather than partway through a scan. With
+    /// `step` driven from an interrupt, `take_frame_complete` tells the main
+    /// loop when the swap has happened.
+    pub fn request_swap(&mut self) {
+        self.swap_pending = true;
+    }
+
+    /// Whether a swap has been requested that hasn't happened yet.
+    pub fn swap_pending(&self) -> bool {
+        self.swap_pending
+    }
+
+    /// Blink the pixel at `(row, col)`, through `transform`, between its
+    /// brightness in `array` and off, on for `on_frames` frames then off for
+    /// `off_frames`, starting now. Blinking a pixel that is already blinking
+    /// restarts it with the new timing. Returns `false` without blinking
+    /// anything if the pixel is off the panel or `MAX_BLINKERS` pixels are
+    /// already blinking.
+    pub fn blink_pixel(&mut self, row: usize, col: usize, on_frames: u32, off_frames: u32) -> bool {
+        let (row, col) = match self.panel_cell(row, col) {
+            Some(cell) => cell,
+            None => return false,
+        };
+        self.stop_blinking(row, col);
+        self.blinkers
+            .push(Blinker {
+                row,
+                col,
+                on_frames,
+                off_frames,
+                start: self.frame_count,
+            })
+            .is_ok()
+    }
+
+    /// Stop the pixel at `(row, col)`, through `transform`, blinking, leaving
+    /// it on.
+    pub fn stop_blink(&mut self, row: usize, col: usize) {
+        if let Some((row, col)) = self.panel_cell(row, col) {
+            self.stop_blinking(row, col);
+        }
+    }
+
+    // the cell in `array` that drawing coordinate `(row, col)` lands on
+    fn panel_cell(&self, row: usize, col: usize) -> Option<(usize, usize)> {
+        self.transform
+            .apply(i32::try_from(col).ok()?, i32::try_from(row).ok()?)
+    }
+
+    // stop the blinker at `array`'s `row`, `col`, if there is one
+    fn stop_blinking(&mut self, row: usize, col: usize) {
+        if let Some(i) = self
+            .blinkers
+            .iter()
+            .position(|b| (b.row, b.col) == (row, col))
+        {
+            self.blinkers.swap_remove(i);
+        }
+    }
+
+    /// Flash the `width` by `height` rectangle with its top left corner at
+    /// `(x, y)` for an alarm: full brightness for `period` frames, then
+    /// whatever is drawn there for `period` frames, and so on, starting now.
+    /// A `period` of 0 holds it at full brightness. The rectangle goes through
+    /// `transform` like `fill_rect`'s. The flash is added as rows are
+    /// prepared, so `array` is untouched and drawing carries on underneath.
+    /// Replaces any earlier alert.
+    pub fn set_alert(&mut self, x: i32, y: i32, width: usize, height: usize, period: u32) {
+        let mut cells = [[false; NUM_COLS]; NUM_ROWS];
+        for (x, y) in draw::rect_cells(self.transform.size(), x, y, width, height) {
+            if let Some((row, col)) = self.transform.apply(x, y) {
+                cells[row][col] = true;
+            }
+        }
+        self.alert = Some((cells, period, self.frame_count));
+    }
+
+    /// Stop the alert flashing, going back to just what is drawn.
+    pub fn clear_alert(&mut self) {
+        self.alert = None;
+    }
+
+    /// The framebuffer being scanned out.
+    fn shown(&self) -> &[[u8; NUM_COLS]; NUM_ROWS] {
+        self.frozen.as_ref().unwrap_or(&self.array)
+    }
+
+    /// The duty cycle out of `max_duty` for a PWM channel driving the output
+    /// enable line to give `brightness`. The line is active low, so the duty
+    /// is the fraction of the time the output is off.
+    pub fn oe_duty(&self, max_duty: u16) -> u16 {
+        let on = (max_duty as u32 * self.brightness as u32 + 127) / 255;
+        max_duty - on as u16
+    }
+
+    /// Set `pwm`, a hardware PWM channel wired to the output enable line, to
+    /// give `brightness`, and enable it. Call it again whenever `brightness`
+    /// changes. With `pwm_brightness` set the pixels are then sent at full
+    /// brightness and the channel does all the dimming, without the steps
+    /// scaling the pixels would cost in grayscale depth.
+    ///
+    /// The driver still drives `output_disable` to blank between rows, so on
+    /// such a board it is typically a pin gating the PWM output, or a dummy.
+    pub fn update_oe_pwm<P>(&self, pwm: &mut P)
+    where
+        P: hal::PwmPin<Duty = u16>,
+    {
+        pwm.set_duty(self.oe_duty(pwm.get_max_duty()));
+        pwm.enable();
+    }
+
+    /// Ramp `brightness` from off up to full over `steps` scans, so the panel
+    /// doesn't draw its full current the instant it turns on.
+    pub fn fade_in<T, PinError>(
+        &mut self,
+        base_freq: T,
+        steps: u32,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        for step in 1..=steps {
+            self.brightness = fade_level(step, steps);
+            self.scan(base_freq)?;
+        }
+        self.brightness = 255;
+        Ok(())
+    }
+
+    /// Run a power on self test for checking a panel at install time: every
+    /// LED at full brightness, then a lit column swept from left to right,
+    /// then everything off. Each pattern, and each step of the sweep, is shown
+    /// for `scans_per_step` scans. `array` is left cleared.
+    pub fn post<T, PinError>(
+        &mut self,
+        base_freq: T,
+        scans_per_step: u32,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        let top = (1 << LAYER_BITS) - 1;
+
+        self.touch();
+        self.array = [[top; NUM_COLS]; NUM_ROWS];
+        for _ in 0..scans_per_step {
+            self.scan(base_freq)?;
+        }
+
+        // straight into `array`, as the clip rectangle and transform are for
+        // drawing and this is a test of the hardware
+        for col in 0..NUM_COLS {
+            self.clear();
+            for row in self.array.iter_mut() {
+                row[col] = top;
+            }
+            for _ in 0..scans_per_step {
+                self.scan(base_freq)?;
+            }
+        }
+
+        self.clear();
+        for _ in 0..scans_per_step {
+            self.scan(base_freq)?;
+        }
+        Ok(())
+    }
+
+    /// Time one `scan` against a free running reference clock and return the
+    /// frame rate it implies.
+    ///
+    /// `now` reads the reference clock, which counts up at `clock_hz` and may
+    /// wrap. Returns 0 if the clock didn't advance during the scan.
+    pub fn measure_fps<T, PinError, C>(
+        &mut self,
+        base_freq: T,
+        clock_hz: u32,
+        mut now: C,
+    ) -> Result<u32, LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+        C: FnMut() -> u32,
+    {
+        let start = now();
+        self.scan(base_freq)?;
+        let elapsed = now().wrapping_sub(start);
+
+        Ok(clock_hz.checked_div(elapsed).unwrap_or(0))
+    }
+
+    /// `refresh`, shortening layers to make up for time lost to anything that
+    /// ran between them, so the frame rate stays steady.
+    ///
+    /// A layer only ends when the next `write_layer` latches over it, so work
+    /// done between calls stretches whichever layer is showing. `now` reads a
+    /// free running clock counting up at `timer_hz`, which may wrap. The time
+    /// each layer actually lasted is compared with what it was started for,
+    /// and the overrun is taken off the following layers, leaving each at
+    /// least one tick. At most one frame's worth of overrun is made up.
+    pub fn refresh_compensated<PinError, C>(
+        &mut self,
+        timer_hz: u32,
+        mut now: C,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        u32: Into<Timer::Time>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+        C: FnMut() -> u32,
+    {
+        let base_freq = self.base_freq.ok_or(LEDError::NoBaseFreq)?;
+        let max_debt = self
+            .max_scan_ticks(base_freq, timer_hz)
+            .min(u32::MAX as u64) as u32;
+        let mut last = self.last_layer_start;
+        let mut debt = self.timing_debt;
+
+        let start_layer = |timer: &mut Timer, layer: usize| {
+            let started = now();
+            if let Some((previous, planned)) = last {
+                let overrun = started.wrapping_sub(previous).saturating_sub(planned);
+                debt = debt.saturating_add(overrun).min(max_debt);
+            }
+
+            let nominal = timer_hz
+                .checked_div(base_freq << (LAYER_BITS - layer - 1))
+                .unwrap_or(0)
+                .max(1);
+            let paid = debt.min(nominal - 1);
+            debt -= paid;
+            let planned = nominal - paid;
+
+            timer.start(timer_hz / planned);
+            last = Some((started, planned));
+        };
+
+        let order = self.frame_order();
+        let mut layers = [[0u8; SPI_BYTES]; LAYER_BITS];
+        let result = self.scan_rows(order, &mut layers, start_layer, || Ok(()));
+        self.last_layer_start = last;
+        self.timing_debt = debt;
+        result
+    }
+
+    /// Lay out the bytes for a whole frame, exactly as they go out over SPI, so
+    /// a circular DMA transfer can refresh the panel without the CPU.
+    ///
+    /// The buffer holds rows `0..NUM_ROWS` in order, and each row holds its
+    /// layers from least to most significant, `SPI_BYTES` apiece. The DMA
+    /// transfer only covers the data, so the rest has to be driven by timers:
+    /// latch `reg_pin` after each layer's bytes, hold layer `n` for `2^n`
+    /// times as long as layer 0, and before the first layer of each row raise
+    /// `output_disable`, move the row pins to the next row, then lower it
+    /// again once the layer is latched. `row_order` and the bit depth are not
+    /// applied.
+    pub fn prepare_dma_buffer(&self, out: &mut [u8; DMA_BUFFER_LEN]) -> Result<(), InvalidRow> {
+        let mut layers = [[0u8; SPI_BYTES]; LAYER_BITS];
+
+        for (row, chunk) in out.chunks_mut(LAYER_BITS * SPI_BYTES).enumerate() {
+            self.prepare_row(row, &mut layers)?;
+
+            for (byte, &data) in chunk.iter_mut().zip(layers.iter().flatten()) {
+                *byte = if self.invert_data { !data } else { data };
+            }
+        }
+        Ok(())
+    }
+
+    /// The number of `timer_hz` ticks one call to `scan(base_freq)` blocks for.
+    ///
+    /// Each layer is timed at `base_freq` scaled by its bit weight, so a row
+    /// takes `2^LAYER_BITS - 1` of the shortest layer's periods.
+    pub fn max_scan_ticks(&self, base_freq: u32, timer_hz: u32) -> u64 {
+        let row_ticks: u64 = (0..LAYER_BITS)
+            .map(|layer| {
+                let freq = base_freq << (LAYER_BITS - layer - 1);
+                timer_hz.checked_div(freq).unwrap_or(0) as u64
+            })
+            .sum();
+        row_ticks * NUM_ROWS as u64
+    }
+
+    /// The frames per second `scan(base_freq)` manages, from
+    /// `max_scan_ticks`.
+    pub fn frame_rate(&self, base_freq: u32, timer_hz: u32) -> u32 {
+        let ticks = self.max_scan_ticks(base_freq, timer_hz);
+        (timer_hz as u64).checked_div(ticks).unwrap_or(0) as u32
+    }
+
+    /// The `base_freq` that makes `scan` run at about `fps` frames per second
+    /// on a `timer_hz` timer, picked so every layer lasts a whole number of
+    /// ticks. Returns 0 if `fps` is 0.
+    pub fn base_freq_for_fps(&self, fps: u16, timer_hz: u32) -> u32 {
+        let frame_ticks = match timer_hz.checked_div(fps as u32) {
+            Some(ticks) => ticks,
+            None => return 0,
+        };
+        // a row lasts 2^LAYER_BITS - 1 of the shortest layer
+        let periods = (NUM_ROWS as u32) * ((1 << LAYER_BITS) - 1);
+        let shortest = ((frame_ticks + periods / 2) / periods).max(1);
+        timer_hz / (shortest << (LAYER_BITS - 1))
+    }
+
+    /// `scan` at about `fps` frames per second on a `timer_hz` timer, working
+    /// out the `base_freq` with `base_freq_for_fps`.
+    pub fn scan_at_fps<PinError>(
+        &mut self,
+        fps: u16,
+        timer_hz: u32,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        u32: Into<Timer::Time>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        let base_freq = self.base_freq_for_fps(fps, timer_hz);
+        self.scan(base_freq)
+    }
+
+    /// How many calls to `scan(base_freq)` on a `timer_hz` timer fill `ms`
+    /// milliseconds, to the nearest whole frame, from `max_scan_ticks`.
+    pub fn scans_for_ms(&self, ms: u32, base_freq: u32, timer_hz: u32) -> u32 {
+        let ticks = self.max_scan_ticks(base_freq, timer_hz);
+        let wanted = ms as u64 * timer_hz as u64 / 1000;
+        (wanted + ticks / 2).checked_div(ticks).unwrap_or(0) as u32
+    }
+
+    /// Keep scanning the current frame for about `ms` milliseconds, for
+    /// showing something for a set time without counting scans by hand.
+    pub fn show_for_ms<PinError>(
+        &mut self,
+        ms: u32,
+        base_freq: u32,
+        timer_hz: u32,
+    ) -> Result<(), LEDError<PinError, SPI::Error>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        u32: Into<Timer::Time>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        for _ in 0..self.scans_for_ms(ms, base_freq, timer_hz) {
+            self.scan(base_freq)?;
+        }
+        Ok(())
+    }
+
+    /// Whether `refresh` runs fast enough to look steady, at least
+    /// `FLICKER_FREE_HZ` frames per second. Worth asserting at startup to
+    /// catch a misconfigured timer. Always `false` before `set_base_freq`.
+    pub fn flicker_ok(&self, timer_hz: u32) -> bool {
+        self.base_freq
+            .is_some_and(|base_freq| self.frame_rate(base_freq, timer_hz) >= FLICKER_FREE_HZ)
+    }
+
+    /// The exact bytes `write_layer` shifts out for layer `layer` of row `row`,
+    /// with `invert_data` applied, for checking wiring without the hardware.
+    /// A row or layer off the end gives the bytes for every pixel off.
+    pub fn layer_bytes(&self, row: usize, layer: usize) -> [u8; SPI_BYTES] {
+        let mut data = [0u8; SPI_BYTES];
+        if let (Ok(brightness), true) = (self.adjusted_row(row), layer < LAYER_BITS) {
+            pack_layer(&brightness, layer, &mut data);
+        }
+        if self.invert_data {
+            for byte in data.iter_mut() {
+                *byte = !*byte;
+            }
+        }
+        data
+    }
+
+    /// Fill `out` with every row's layers as `prepare_row` would, without
+    /// touching the hardware, for checking a frame's output against known
+    /// good bytes. Like `prepare_row` the data isn't inverted.
+    pub fn prepare_frame(
+        &self,
+        out: &mut [[[u8; SPI_BYTES]; LAYER_BITS]; NUM_ROWS],
+    ) -> Result<(), InvalidRow> {
+        for (row, layers) in out.iter_mut().enumerate() {
+            self.prepare_row(row, layers)?;
+        }
+        Ok(())
+    }
+
+    pub fn prepare_row(
+        &self,
+        r: usize,
+        buf: &mut [[u8; SPI_BYTES]; LAYER_BITS],
+    ) -> Result<(), InvalidRow> {
+        pack_row(&self.adjusted_row(r)?, buf);
+        Ok(())
+    }
+
+    /// Whether every row comes out of `adjusted_row` with every pixel off,
+    /// taking the overlay, inversion and everything else into account.
+    fn shows_nothing(&self) -> bool {
+        (0..NUM_ROWS).all(|r| {
+            self.adjusted_row(r)
+                .is_ok_and(|row| row.iter().all(|&brightness| brightness == 0))
+        })
+    }
+
+    /// Row `r` as it will be shown, with the overlay, blinking, inversion,
+    /// alert, brightness, calibration, timing correction and shape mask
+    /// applied, in that order.
+    fn adjusted_row(&self, r: usize) -> Result<[u8; NUM_COLS], InvalidRow> {
+        let mut row = *self.shown().get(r).ok_or(InvalidRow(r))?;
+        if let Some(overlay) = self.overlay.as_ref().and_then(|overlay| overlay.0.get(r)) {
+            for (brightness, &over) in row.iter_mut().zip(overlay.iter()) {
+                if over != 0 {
+                    *brightness = over;
+                }
+            }
+        }
+        for blinker in self.blinkers.iter().filter(|b| b.row == r) {
+            if !blinker.is_on(self.frame_count) {
+                if let Some(brightness) = row.get_mut(blinker.col) {
+                    *brightness = 0;
+                }
+            }
+        }
+        if self.inverted {
+            let top: u8 = (1 << LAYER_BITS) - 1;
+            for brightness in row.iter_mut() {
+                *brightness = top.saturating_sub(*brightness);
+            }
+        }
+        if let Some((cells, period, start)) = &self.alert {
+            let elapsed = self.frame_count.wrapping_sub(*start);
+            let lit = *period == 0 || (elapsed / *period) % 2 == 0;
+            if lit {
+                let top_level: u8 = (1 << LAYER_BITS) - 1;
+                for (brightness, &flashes) in row.iter_mut().zip(cells[r].iter()) {
+                    if flashes {
+                        *brightness = top_level;
+                    }
+                }
+            }
+        }
+        let global = if self.pwm_brightness {
+            255
+        } else {
+            self.brightness
+        };
+        let factor = scale(global, self.row_brightness[r]);
+        for brightness in row.iter_mut() {
+            *brightness = scale(*brightness, factor);
+        }
+        if let Some(calibration) = self.calibration {
+            for (brightness, &factor) in row.iter_mut().zip(calibration[r].iter()) {
+                *brightness = scale(*brightness, factor);
+            }
+        }
+
+        for brightness in row.iter_mut() {
+            if let Some(&corrected) = self.timing_correction.get(*brightness as usize) {
+                *brightness = corrected;
+            }
+        }
+        if let Some(mask) = self.shape_mask {
+            for (brightness, &exists) in row.iter_mut().zip(mask[r].iter()) {
+                if !exists {
+                    *brightness = 0;
+                }
+            }
+        }
+        Ok(row)
+    }
+}
+
+/// A pixel set blinking by `blink_pixel`.
+struct Blinker {
+    row: usize,
+    col: usize,
+    on_frames: u32,
+    off_frames: u32,
+    // the frame it started blinking on
+    start: u32,
+}
+
+impl Blinker {
+    fn is_on(&self, frame: u32) -> bool {
+        let period = self.on_frames.saturating_add(self.off_frames);
+        period == 0 || frame.wrapping_sub(self.start) % period < self.on_frames
+    }
+}
+
+/// The brightness `fade_in` shows on `step` of `steps`.
+fn fade_level(step: u32, steps: u32) -> u8 {
+    (255 * step as u64 / steps.max(1) as u64) as u8
+}
+
+/// Raise `pin`, then drive it high `ticks` more times to hold the pulse.
+fn pulse<P: OutputPin>(pin: &mut P, ticks: u32) -> Result<(), P::Error> {
+    for _ in 0..=ticks {
+        pin.set_high()?;
+    }
+    Ok(())
+}
+
+/// Spin until `timer` fires, giving up with `LEDError::TimerStuck` after
+/// `max_spins` polls if given.
+fn wait_bounded<Timer, P, S>(
+    timer: &mut Timer,
+    max_spins: Option<u32>,
+) -> Result<(), LEDError<P, S>>
+where
+    Timer: hal::timer::CountDown,
+{
+    let mut spins = 0u32;
+    loop {
+        match timer.wait() {
+            Ok(()) => return Ok(()),
+            Err(nb::Error::WouldBlock) => {
+                spins = spins.saturating_add(1);
+                if max_spins.is_some_and(|max| spins > max) {
+                    return Err(LEDError::TimerStuck);
+                }
+            }
+            Err(nb::Error::Other(void)) => match void {},
+        }
+    }
+}
+
+/// Start each layer's timer at `base_freq` scaled by the layer's bit weight,
+/// so layer 0 is the shortest.
+fn weighted<Timer, T>(base_freq: T) -> impl FnMut(&mut Timer, usize)
+where
+    Timer: hal::timer::CountDown,
+    T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+{
+    move |timer, layer| timer.start(base_freq << (LAYER_BITS - layer - 1))
+}
+
+/// Scale `value` by `factor / 255`, rounding to the nearest step. Truncating
+/// would lose up to a step at each stage scaling is applied in.
+fn scale(value: u8, factor: u8) -> u8 {
+    ((value as u16 * factor as u16 + 127) / 255) as u8
+}
+
+/// Pack bit `layer` of each brightness in `row` into `out`, one bit per column.
+///
+/// Column 0 lands in the least significant bit of the last byte, so `out` can
+/// be shifted out most significant byte first. If `row` doesn't fill `out`,
+/// as with 12 columns in 2 bytes, the spare high bits of the first byte are
+/// left off.
+pub fn pack_layer(row: &[u8], layer: usize, out: &mut [u8]) {
+    for byte in out.iter_mut() {
+        *byte = 0;
+    }
+
+    let len = out.len();
+    for (col, brightness) in row.iter().enumerate().take(len * 8) {
+        // grab bit `layer` of the brightness
+        let bit = brightness.checked_shr(layer as u32).unwrap_or(0) & 1;
+        out[len - 1 - col / 8] |= bit << (col % 8);
+    }
+}
+
+/// Pack every layer of `row` into `buf`, as `prepare_row` does with a row of
+/// `NUM_COLS`. Any bits past the end of `row` are left off.
+fn pack_row<const BYTES: usize>(row: &[u8], buf: &mut [[u8; BYTES]; LAYER_BITS]) {
+    for (layer, out) in buf.iter_mut().enumerate() {
+        pack_layer(row, layer, out);
+    }
+}
+
+/// Whether `mode` is the mode the shift registers expect, for asserting on
+/// in bring-up code. A wrong mode usually shows up as columns shifted by one.
+pub fn check_spi_mode(mode: hal::spi::Mode) -> bool {
+    mode == EXPECTED_SPI_MODE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    pub(crate) mod mock;
+    use mock::*;
+
+    pub(crate) fn mock_array(
+    ) -> LEDArray<MockPin, MockPin, MockPin, MockTimer, MockSPI, MockPin, MockPin> {
+        LEDArray::new(
+            (MockPin::new(), MockPin::new(), MockPin::new()),
+            MockTimer { tries: 0 },
+            MockSPI::new(),
+            MockPin::new(),
+            MockPin::new(),
+        )
+    }
+
+    /// Like `mock_array`, but with a timer that keeps every duration it was
+    /// started with.
+    fn recording_array(
+    ) -> LEDArray<MockPin, MockPin, MockPin, RecordingTimer, MockSPI, MockPin, MockPin> {
+        LEDArray::new(
+            (MockPin::new(), MockPin::new(), MockPin::new()),
+            RecordingTimer::new(),
+            MockSPI::new(),
+            MockPin::new(),
+            MockPin::new(),
+        )
+    }
+
+    #[test]
+    fn test_prepare_row() {
+        let row = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0];
+
+        let mut array = mock_array();
+        array.array = [row; 8];
+
+        let mut buf = [[0u8; 2]; 4];
+        array.prepare_row(0, &mut buf).unwrap();
+
+        assert_eq!(buf[0], [0b01010101, 0b01010101]); // for 1s
+        assert_eq!(buf[1], [0b00110011, 0b00110011]); // for 2s
+        assert_eq!(buf[2], [0b00001111, 0b00001111]); // for 4s
+        assert_eq!(buf[3], [0b00000000, 0b11111111]); // for 8s
+    }
+
+    #[test]
+    fn test_write_layer() {
+        let mut array = mock_array();
+
+        array.timer.tries = 6;
+        array.reg_pin.set_high().unwrap();
+        array.write_layer(&[0x57, 0x3f], None).unwrap_or(());
+        assert_eq!(array.spi.written, [0xa8, 0xc0]);
+        assert_eq!(array.timer.tries, 0);
+        assert_eq!(array.reg_pin.cycles, 1);
+        assert_eq!(array.output_disable.cycles, 0);
+        assert!(!array.output_disable.state);
+
+        array.write_layer(&[13, 0], Some(3)).unwrap_or(());
+        assert_eq!(array.reg_pin.cycles, 2);
+        assert_eq!(array.output_disable.cycles, 1);
+        assert!(!array.output_disable.state);
+        assert!(!array.row_pins.2.state);
+        assert!(array.row_pins.1.state);
+        assert!(array.row_pins.0.state);
+    }
+
+    #[test]
+    fn test_write_layer_length_mismatch() {
+        let mut array = mock_array();
+
+        let result = array.write_layer(&[0x57], None);
+        assert!(matches!(
+            result,
+            Err(LEDError::LengthMismatch {
+                expected: 2,
+                got: 1
+            })
+        ));
+        assert!(array.spi.written.is_empty());
+        assert_eq!(array.reg_pin.cycles, 0);
+    }
+
+    #[test]
+    fn test_write_empty_layer() {
+        let mut array = mock_array();
+        array.reg_pin.state = true;
+
+        let result = array.write_layer(&[], Some(1));
+        assert!(matches!(
+            result,
+            Err(LEDError::LengthMismatch {
+                expected: 2,
+                got: 0
+            })
+        ));
+        assert!(array.spi.written.is_empty());
+        // the latch wasn't touched and the row didn't change
+        assert!(array.reg_pin.state);
+        assert_eq!(array.reg_pin.cycles, 0);
+        assert!(!array.row_pins.0.state);
+    }
+
+    #[test]
+    fn test_write_only_spi() {
+        let mut array = LEDArray::new(
+            (MockPin::new(), MockPin::new(), MockPin::new()),
+            MockTimer { tries: 0 },
+            MockWriteSPI {
+                written: heapless::Vec::new(),
+            },
+            MockPin::new(),
+            MockPin::new(),
+        );
+
+        array.write_layer(&[0x57, 0x3f], Some(1)).unwrap_or(());
+        assert_eq!(array.spi.written, [0xa8, 0xc0]);
+    }
+
+    #[test]
+    fn test_on_frame() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+        static FRAMES: AtomicU32 = AtomicU32::new(0);
+
+        let mut array = mock_array();
+        array.on_frame = Some(|| {
+            FRAMES.fetch_add(1, Ordering::Relaxed);
+        });
+
+        for _ in 0..3 {
+            array.scan(1).unwrap_or(());
+        }
+        assert_eq!(FRAMES.load(Ordering::Relaxed), 3);
+    }
+
+    #[test]
+    fn test_max_scan_ticks() {
+        let array = mock_array();
+
+        // layers last 125, 250, 500 and 1000 ticks, for each of 8 rows
+        assert_eq!(array.max_scan_ticks(1_000, 1_000_000), 15_000);
+    }
+
+    #[test]
+    fn test_flicker_ok() {
+        let mut array = mock_array();
+        assert!(!array.flicker_ok(1_000_000));
+
+        // 15ms a frame
+        array.set_base_freq(1_000);
+        assert_eq!(array.frame_rate(1_000, 1_000_000), 66);
+        assert!(!array.flicker_ok(1_000_000));
+
+        // about 3.7ms a frame, the shortest layer rounding down
+        array.set_base_freq(4_000);
+        assert_eq!(array.frame_rate(4_000, 1_000_000), 267);
+        assert!(array.flicker_ok(1_000_000));
+    }
+
+    #[test]
+    fn test_refresh_compensated() {
+        let mut array = recording_array();
+        array.array[0][0] = 15;
+        // layers should last 10, 20, 40 and 80 ticks
+        array.set_base_freq(1_000);
+
+        // the first layer runs 15 ticks over, then everything is on time
+        let times = [0u32, 25, 30, 70];
+        let mut calls = 0;
+        let clock = || {
+            let time = times.get(calls).copied().unwrap_or(u32::MAX);
+            calls += 1;
+            time
+        };
+        // only check the first row's timings, the rest of the clock is bogus
+        array.refresh_compensated(80_000, clock).unwrap_or(());
+
+        // 20 ticks cut to 5 to pay back the 15
+        assert_eq!(array.timer.started[..4], [8_000, 16_000, 2_000, 1_000]);
+    }
+
+    #[test]
+    fn test_scan_at_fps() {
+        let mut array = recording_array();
+
+        let base_freq = array.base_freq_for_fps(60, 1_000_000);
+        assert_eq!(base_freq, 899);
+        assert!((59..=61).contains(&array.frame_rate(base_freq, 1_000_000)));
+        assert_eq!(array.base_freq_for_fps(0, 1_000_000), 0);
+
+        array.scan_at_fps(60, 1_000_000).unwrap();
+        assert_eq!(array.timer.started[..LAYER_BITS], [7192, 3596, 1798, 899]);
+    }
+
+    #[test]
+    fn test_show_for_ms() {
+        let mut array = recording_array();
+
+        // a frame takes 9960 ticks, just over 100 per second
+        let base_freq = array.base_freq_for_fps(100, 1_000_000);
+        assert_eq!(array.max_scan_ticks(base_freq, 1_000_000), 9960);
+        assert_eq!(array.scans_for_ms(2_000, base_freq, 1_000_000), 201);
+        assert_eq!(array.scans_for_ms(0, base_freq, 1_000_000), 0);
+        assert_eq!(array.scans_for_ms(2_000, 0, 1_000_000), 0);
+
+        array.show_for_ms(30, base_freq, 1_000_000).unwrap();
+        assert_eq!(array.frame_count, 3);
+    }
+
+    #[test]
+    fn test_scan_group() {
+        let mut panels = [mock_array(), mock_array()];
+        panels[0].array[1][0] = 15;
+        panels[1].array[6][9] = 3;
+        let mut timer = RecordingTimer::new();
+
+        LEDArray::scan_group(&mut panels, &mut timer, 1u32).unwrap();
+
+        // one shared timer cycle per layer moves both panels on one frame
+        assert_eq!(timer.started.len(), NUM_ROWS * LAYER_BITS);
+        assert_eq!(timer.started[..LAYER_BITS], [8, 4, 2, 1]);
+        assert!(panels.iter().all(|panel| panel.frame_count == 1));
+        assert!(panels.iter().all(|panel| panel.timer.tries == 0));
+
+        // each sends what it would have on its own
+        for panel in panels.iter() {
+            let mut alone = mock_array();
+            alone.array = panel.array;
+            alone.scan(1).unwrap_or(());
+            assert_eq!(panel.spi.written, alone.spi.written);
+        }
+    }
+
+    #[test]
+    fn test_scan_group_timer_stuck() {
+        let mut panels = [mock_array(), mock_array()];
+        panels[1].max_timer_spins = Some(10);
+        // a shared timer that won't expire within the limit
+        let mut timer = MockTimer { tries: i32::MAX };
+        assert!(matches!(
+            LEDArray::scan_group(&mut panels, &mut timer, 1),
+            Err(LEDError::TimerStuck)
+        ));
+        assert_eq!(timer.tries, i32::MAX - 11);
+    }
+
+    #[test]
+    fn test_scan_custom_layers() {
+        let mut array = recording_array();
+        array.array[1][0] = 0b101;
+
+        array.scan_custom_layers(&[5u32, 7, 9]).unwrap();
+
+        assert_eq!(array.spi.written.len(), NUM_ROWS * 3 * SPI_BYTES);
+        assert_eq!(array.timer.started[..6], [5, 7, 9, 5, 7, 9]);
+        // row 1 shows bits 0 and 2 but not bit 1
+        let row = &array.spi.written[3 * SPI_BYTES..6 * SPI_BYTES];
+        assert_eq!(row, [0xff, 0xfe, 0xff, 0xff, 0xff, 0xfe]);
+        assert_eq!(array.frame_count, 1);
+    }
+
+    /// Recover which rows were written from the first byte of each row's
+    /// first layer, given row `r` has only column `r` lit.
+    fn scanned_rows(written: &[u8]) -> heapless::Vec<u32, 8> {
+        written
+            .chunks(SPI_BYTES * LAYER_BITS)
+            .map(|row| (!row[1]).trailing_zeros())
+            .collect()
+    }
+
+    #[test]
+    fn test_scan_interlaced() {
+        let mut array = mock_array();
+        for r in 0..NUM_ROWS {
+            array.array[r][r] = 15;
+        }
+
+        array.scan_interlaced(1).unwrap_or(());
+        assert_eq!(scanned_rows(&array.spi.written), [0, 2, 4, 6]);
+        assert_eq!(array.frame_count, 1);
+
+        array.spi.written.clear();
+        array.scan_interlaced(1).unwrap_or(());
+        assert_eq!(scanned_rows(&array.spi.written), [1, 3, 5, 7]);
+        assert_eq!(array.frame_count, 2);
+    }
+
+    #[test]
+    fn test_pack_layer() {
+        let row = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0];
+        let mut out = [0xffu8; 2];
+
+        pack_layer(&row, 0, &mut out);
+        assert_eq!(out, [0b01010101, 0b01010101]);
+        pack_layer(&row, 1, &mut out);
+        assert_eq!(out, [0b00110011, 0b00110011]);
+        pack_layer(&row, 2, &mut out);
+        assert_eq!(out, [0b00001111, 0b00001111]);
+        pack_layer(&row, 3, &mut out);
+        assert_eq!(out, [0b00000000, 0b11111111]);
+    }
+
+    #[test]
+    fn test_pack_layer_padding() {
+        // a 12 column panel still takes two bytes
+        let row = [15, 0, 15, 0, 15, 0, 15, 0, 15, 15, 15, 1];
+        let mut out = [0xffu8; 2];
+
+        pack_layer(&row, 0, &mut out);
+        assert_eq!(out, [0b0000_1111, 0b01010101]);
+        pack_layer(&row, 3, &mut out);
+        assert_eq!(out, [0b0000_0111, 0b01010101]);
+    }
+
+    #[test]
+    fn test_prepare_row_padding() {
+        // the rows of a 12 column panel, packed the way `prepare_row` does
+        let row = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4];
+        let mut buf = [[0xffu8; 2]; LAYER_BITS];
+
+        pack_row(&row, &mut buf);
+        assert_eq!(buf[0], [0b0000_0101, 0b01010101]); // for 1s
+        assert_eq!(buf[1], [0b0000_0011, 0b00110011]); // for 2s
+        assert_eq!(buf[2], [0b0000_1111, 0b00001111]); // for 4s
+        assert_eq!(buf[3], [0b0000_0000, 0b11111111]); // for 8s
+
+        // and a full width row leaves nothing spare
+        let mut array = mock_array();
+        array.array = [[15; NUM_COLS]; NUM_ROWS];
+        let mut buf = [[0u8; SPI_BYTES]; LAYER_BITS];
+        array.prepare_row(0, &mut buf).unwrap_or(());
+        assert!(buf.iter().flatten().all(|&byte| byte == 0xff));
+    }
+
+    #[test]
+    fn test_row_order() {
+        let mut array = mock_array();
+        for r in 0..NUM_ROWS {
+            array.array[r][r] = 15;
+        }
+        array.row_order = [0, 4, 1, 5, 2, 6, 3, 7];
+
+        array.scan(1).unwrap_or(());
+        assert_eq!(scanned_rows(&array.spi.written), [0, 4, 1, 5, 2, 6, 3, 7]);
+
+        array.spi.written.clear();
+        array.scan_interlaced(1).unwrap_or(());
+        assert_eq!(scanned_rows(&array.spi.written), [1, 5, 3, 7]);
+    }
+
+    #[test]
+    fn test_reverse_scan_each_frame() {
+        let mut array = mock_array();
+        for r in 0..NUM_ROWS {
+            array.array[r][r] = 15;
+        }
+        array.reverse_scan_each_frame = true;
+
+        array.scan(1).unwrap_or(());
+        assert_eq!(scanned_rows(&array.spi.written), [0, 1, 2, 3, 4, 5, 6, 7]);
+
+        array.spi.written.clear();
+        array.scan(1).unwrap_or(());
+        assert_eq!(scanned_rows(&array.spi.written), [7, 6, 5, 4, 3, 2, 1, 0]);
+
+        array.spi.written.clear();
+        array.scan(1).unwrap_or(());
+        assert_eq!(scanned_rows(&array.spi.written), [0, 1, 2, 3, 4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_stagger_layers() {
+        let mut array = mock_array();
+        // only the most significant layer has anything lit
+        array.array = [[8; NUM_COLS]; NUM_ROWS];
+        array.stagger_layers = true;
+
+        array.scan(1).unwrap_or(());
+
+        let msb_position: heapless::Vec<usize, 8> = array
+            .spi
+            .written
+            .chunks(SPI_BYTES * LAYER_BITS)
+            .map(|row| {
+                row.chunks(SPI_BYTES)
+                    .position(|layer| layer == [0, 0])
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(msb_position, [3, 2, 1, 0, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_error_paths() {
+        let mut array = mock_array();
+
+        let mut buf = [[0u8; SPI_BYTES]; LAYER_BITS];
+        assert_eq!(
+            array.prepare_row(NUM_ROWS, &mut buf),
+            Err(InvalidRow(NUM_ROWS))
+        );
+
+        array.row_order[3] = 9;
+        assert!(matches!(array.scan(1), Err(LEDError::InvalidRow(9))));
+
+        assert_eq!(array.max_scan_ticks(0, 1_000_000), 0);
+    }
+
+    /// Recover the brightness of column 0 in each row written, from that
+    /// column's bit in each of the row's layers.
+    fn scanned_brightness(written: &[u8]) -> heapless::Vec<u8, 64> {
+        written
+            .chunks(SPI_BYTES * LAYER_BITS)
+            .map(|row| {
+                row.chunks(SPI_BYTES)
+                    .enumerate()
+                    .map(|(layer, bytes)| (!bytes[SPI_BYTES - 1] & 1) << layer)
+                    .sum()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fade_in() {
+        let mut array = mock_array();
+        array.array = [[15; NUM_COLS]; NUM_ROWS];
+
+        array.fade_in(1, 4).unwrap_or(());
+
+        let frames: heapless::Vec<u8, 4> = scanned_brightness(&array.spi.written)
+            .chunks(NUM_ROWS)
+            .map(|rows| rows[0])
+            .collect();
+        assert_eq!(frames, [4, 7, 11, 15]);
+        assert_eq!(array.brightness, 255);
+
+        // long fades don't overflow
+        assert_eq!(fade_level(1, u32::MAX), 0);
+        assert_eq!(fade_level(u32::MAX / 2, u32::MAX), 127);
+        assert_eq!(fade_level(u32::MAX, u32::MAX), 255);
+    }
+
+    #[test]
+    fn test_all_rows_on() {
+        let mut array = mock_array();
+        let mut enable = MockPin::new();
+        array.row_pins.1.set_high().unwrap();
+
+        array.all_rows_on(&mut enable).unwrap();
+        assert!(enable.state);
+        assert!(!array.row_pins.0.state);
+        assert!(array.row_pins.1.state);
+        assert!(!array.row_pins.2.state);
+        assert_eq!(array.row_pins.1.cycles, 0);
+
+        array.all_rows_off(&mut enable).unwrap();
+        assert!(!enable.state);
+        assert!(array.row_pins.1.state);
+    }
+
+    #[test]
+    fn test_post() {
+        let mut array = mock_array();
+        array.array[3][3] = 7;
+
+        array.post(1, 1).unwrap_or(());
+
+        let frame_bytes = NUM_ROWS * LAYER_BITS * SPI_BYTES;
+        let frames: heapless::Vec<&[u8], 32> = array.spi.written.chunks(frame_bytes).collect();
+        assert_eq!(frames.len(), NUM_COLS + 2);
+
+        // all on first
+        assert!(frames[0].iter().all(|&b| b == 0x00));
+        // then the sweep, each frame lighting one column on every row
+        for (col, frame) in frames[1..=NUM_COLS].iter().enumerate() {
+            let lit = [!(1u16 << col) as u8, !((1u16 << col) >> 8) as u8];
+            assert_eq!(frame[..SPI_BYTES], [lit[1], lit[0]]);
+            assert!(frame
+                .chunks(SPI_BYTES)
+                .all(|layer| layer == [lit[1], lit[0]]));
+        }
+        // then all off
+        assert!(frames[NUM_COLS + 1].iter().all(|&b| b == 0xff));
+        assert!(array.is_blank());
+
+        // clipping and turning the drawing area don't change the test
+        let mut turned = mock_array();
+        turned.set_clip(0, 0, 1, 1);
+        turned.transform.rotation = Rotation::Deg90;
+        turned.post(1, 1).unwrap_or(());
+        assert_eq!(turned.spi.written, array.spi.written);
+    }
+
+    #[test]
+    fn test_measure_fps() {
+        let mut array = mock_array();
+
+        // a 1MHz clock that reads 2000 ticks later after the scan
+        let mut ticks = [4_000_000_000u32, 4_000_002_000].iter().copied();
+        let fps = array.measure_fps(1, 1_000_000, || ticks.next().unwrap_or(0));
+        assert!(matches!(fps, Ok(500)));
+
+        // the clock wraps during the scan
+        let mut ticks = [u32::MAX - 999, 1_000].iter().copied();
+        let fps = array.measure_fps(1, 1_000_000, || ticks.next().unwrap_or(0));
+        assert!(matches!(fps, Ok(500)));
+    }
+
+    #[test]
+    fn test_calibration() {
+        static HALF: [[u8; NUM_COLS]; NUM_ROWS] = [[128; NUM_COLS]; NUM_ROWS];
+        let mut array = mock_array();
+        array.array = [[14; NUM_COLS]; NUM_ROWS];
+        array.calibration = Some(&HALF);
+
+        let mut buf = [[0u8; SPI_BYTES]; LAYER_BITS];
+        for row in 0..NUM_ROWS {
+            array.prepare_row(row, &mut buf).unwrap();
+            // every cell is now 7
+            assert_eq!(buf, [[0xff; 2], [0xff; 2], [0xff; 2], [0x00; 2]]);
+        }
+    }
+
+    #[test]
+    fn test_shape_mask() {
+        const fn round() -> [[bool; NUM_COLS]; NUM_ROWS] {
+            let mut mask = [[true; NUM_COLS]; NUM_ROWS];
+            mask[0][0] = false;
+            mask[0][15] = false;
+            mask[7][0] = false;
+            mask[7][15] = false;
+            mask
+        }
+        static ROUND: [[bool; NUM_COLS]; NUM_ROWS] = round();
+
+        let mut array = mock_array();
+        array.array = [[15; NUM_COLS]; NUM_ROWS];
+        array.shape_mask = Some(&ROUND);
+        // rows 0 and 7 come out full once inverted, but the corners stay off
+        array.array[0] = [0; NUM_COLS];
+        array.array[7] = [0; NUM_COLS];
+        array.set_inverted(true);
+
+        let mut buf = [[0u8; SPI_BYTES]; LAYER_BITS];
+        for row in [0, 7].iter().copied() {
+            array.prepare_row(row, &mut buf).unwrap();
+            assert_eq!(buf, [[0x7f, 0xfe]; LAYER_BITS]);
+        }
+        array.prepare_row(1, &mut buf).unwrap();
+        assert_eq!(buf, [[0x00; SPI_BYTES]; LAYER_BITS]);
+    }
+
+    #[test]
+    fn test_scale_rounds() {
+        assert_eq!(scale(255, 128), 128);
+        assert_eq!(scale(255, 255), 255);
+        assert_eq!(scale(0, 255), 0);
+        // 15 * 0.5 = 7.5 rounds up, where it used to truncate to 7
+        assert_eq!(scale(15, 128), 8);
+        assert_eq!(scale(15, 127), 7);
+    }
+
+    #[test]
+    fn test_overlay() {
+        let mut array = mock_array();
+        array.array[0] = [4; NUM_COLS];
+        let mut overlay = Frame::zeroed();
+        overlay.0[0][0] = 1;
+        overlay.0[1][15] = 8;
+        array.overlay = Some(overlay);
+
+        let mut buf = [[0u8; SPI_BYTES]; LAYER_BITS];
+        array.prepare_row(0, &mut buf).unwrap();
+        // the 1 replaces a 4 in the first column
+        assert_eq!(buf, [[0x00, 0x01], [0x00; 2], [0xff, 0xfe], [0x00; 2]]);
+
+        array.prepare_row(1, &mut buf).unwrap();
+        assert_eq!(buf, [[0x00; 2], [0x00; 2], [0x00; 2], [0x80, 0x00]]);
+        assert_eq!(array.array[1][15], 0);
+
+        // an overlay alone is enough to stop a frame counting as blank
+        array.array = [[0; NUM_COLS]; NUM_ROWS];
+        array.skip_blank = true;
+        array.scan(1).unwrap_or(());
+        assert!(!array.spi.written.is_empty());
+    }
+
+    #[test]
+    fn test_timing_correction() {
+        let mut array = mock_array();
+        array.array[0] = [1; NUM_COLS];
+        array.array[0][0] = 2;
+        array.timing_correction[1] = 3;
+
+        let mut buf = [[0u8; SPI_BYTES]; LAYER_BITS];
+        array.prepare_row(0, &mut buf).unwrap();
+        // the 1s went out as 3, the 2 was left alone
+        assert_eq!(buf, [[0xff, 0xfe], [0xff, 0xff], [0x00; 2], [0x00; 2]]);
+    }
+
+    #[test]
+    fn test_freeze() {
+        let mut array = mock_array();
+        array.array[2][3] = 15;
+        array.scan(1).unwrap_or(());
+        let before = array.spi.written.clone();
+
+        array.freeze();
+        array.array = [[0; NUM_COLS]; NUM_ROWS];
+        array.array[5][5] = 15;
+        array.spi.written.clear();
+        array.scan(1).unwrap_or(());
+        assert!(array.is_frozen());
+        assert_eq!(array.spi.written, before);
+
+        array.unfreeze();
+        array.spi.written.clear();
+        array.scan(1).unwrap_or(());
+        assert_ne!(array.spi.written, before);
+        assert_eq!(array.array[5][5], 15);
+    }
+
+    #[test]
+    fn test_blink_pixel() {
+        let mut array = mock_array();
+        array.array[2][3] = 15;
+        array.array[2][4] = 15;
+        array.frame_count = 10;
+        assert!(array.blink_pixel(2, 3, 2, 1));
+        assert!(!array.blink_pixel(NUM_ROWS, 3, 2, 1));
+
+        let mut seen = [0; 6];
+        for cell in seen.iter_mut() {
+            let row = array.adjusted_row(2).unwrap_or_default();
+            assert_eq!(row[4], 15);
+            *cell = row[3];
+            array.frame_count += 1;
+        }
+        assert_eq!(seen, [15, 15, 0, 15, 15, 0]);
 
-        array.timer.tries = 6;
+        array.stop_blink(2, 3);
+        assert_eq!(array.adjusted_row(2).unwrap_or_default()[3], 15);
+    }
+
+    #[test]
+    fn test_blink_pixel_transformed() {
+        let mut array = mock_array();
+        array.transform.rotation = Rotation::Deg90;
+        array.array[0][15] = 15;
+        array.array[7][0] = 15;
+
+        // drawing coordinates run NUM_ROWS wide and NUM_COLS tall once turned
+        assert!(array.blink_pixel(0, 0, 0, 1));
+        assert!(array.blink_pixel(15, 7, 0, 1));
+        assert!(!array.blink_pixel(0, NUM_ROWS, 0, 1));
+        assert_eq!(array.adjusted_row(0).unwrap_or_default()[15], 0);
+        assert_eq!(array.adjusted_row(7).unwrap_or_default()[0], 0);
+
+        array.stop_blink(15, 7);
+        assert_eq!(array.adjusted_row(7).unwrap_or_default()[0], 15);
+        assert_eq!(array.adjusted_row(0).unwrap_or_default()[15], 0);
+    }
+
+    #[test]
+    fn test_request_swap() {
+        let mut array = mock_array();
+        array.freeze();
+        array.step(1).unwrap_or(false);
+
+        // drawn partway through a frame, so it has to wait for the next one
+        array.array[7][0] = 15;
+        array.request_swap();
+        while !array.step(1).unwrap_or(true) {}
+        assert!(array.swap_pending());
+        assert_eq!(array.shown()[7][0], 0);
+
+        array.step(1).unwrap_or(false);
+        assert!(!array.swap_pending());
+        assert_eq!(array.shown()[7][0], 15);
+
+        // held until the next swap
+        array.array[7][0] = 0;
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.shown()[7][0], 15);
+    }
+
+    #[test]
+    fn test_update_oe_pwm() {
+        let mut array = mock_array();
+        let mut pwm = MockPwm {
+            duty: 0,
+            max_duty: 1000,
+            enabled: false,
+        };
+
+        array.brightness = 64;
+        array.update_oe_pwm(&mut pwm);
+        // on a quarter of the time, so disabled the other three quarters
+        assert_eq!(pwm.duty, 749);
+        assert!(pwm.enabled);
+
+        array.brightness = 255;
+        array.update_oe_pwm(&mut pwm);
+        assert_eq!(pwm.duty, 0);
+
+        // the pixels themselves aren't dimmed as well
+        array.brightness = 64;
+        array.pwm_brightness = true;
+        array.array[0][0] = 15;
+        assert_eq!(array.adjusted_row(0).unwrap_or_default()[0], 15);
+    }
+
+    #[test]
+    fn test_generation() {
+        let mut array = mock_array();
+        let mut last = array.generation();
+        let mut bumped = |array: &LEDArray<_, _, _, _, _, _, _>| {
+            let changed = array.generation() != last;
+            last = array.generation();
+            changed
+        };
+
+        array.set_pixel(1, 1, 5);
+        assert!(bumped(&array));
+        array.fill_rect(0, 0, 2, 2, 3);
+        assert!(bumped(&array));
+        array.clear();
+        assert!(bumped(&array));
+        array.load_frame(&Frame::filled(4));
+        assert!(bumped(&array));
+        array.gradient_fill(0, 15, Direction::Horizontal);
+        assert!(bumped(&array));
+
+        // reading and scanning leave it alone
+        let _ = array.get_pixel(1, 1);
+        let _ = array.is_blank();
+        array.scan(1).unwrap_or(());
+        assert!(!bumped(&array));
+    }
+
+    #[test]
+    fn test_try_step() {
+        let mut array = LEDArray::new(
+            (MockPin::new(), MockPin::new(), MockPin::new()),
+            CountingTimer { left: 0 },
+            MockSPI::new(),
+            MockPin::new(),
+            MockPin::new(),
+        );
+
+        // nothing is running yet, so the first layer goes straight out
+        assert!(matches!(array.try_step(1u32), Ok(false)));
+        assert_eq!(array.spi.written.len(), SPI_BYTES);
+
+        // then it holds off for the 8 ticks of layer 0
+        assert_eq!(array.ticks_until_step(), 8);
+        while array.timer.left > 0 {
+            assert!(matches!(array.try_step(1u32), Err(nb::Error::WouldBlock)));
+            array.timer.left -= 1;
+        }
+        assert_eq!(array.spi.written.len(), SPI_BYTES);
+        assert!(matches!(array.try_step(1u32), Ok(false)));
+        assert_eq!(array.spi.written.len(), 2 * SPI_BYTES);
+        assert_eq!(array.ticks_until_step(), 4);
+    }
+
+    #[test]
+    fn test_set_alert() {
+        let mut array = mock_array();
+        array.array[1] = [3; NUM_COLS];
+        array.frame_count = 5;
+        array.set_alert(2, 1, 3, 2, 2);
+
+        let mut seen = [0; 6];
+        for cell in seen.iter_mut() {
+            let row = array.adjusted_row(1).unwrap_or_default();
+            // only columns 2 to 4 flash
+            assert_eq!(row[..2], [3, 3]);
+            assert_eq!(row[5..], [3; NUM_COLS - 5]);
+            *cell = row[2];
+            // and row 3 is outside the alert
+            assert_eq!(array.adjusted_row(3).unwrap_or_default()[2], 0);
+            array.frame_count += 1;
+        }
+        assert_eq!(seen, [15, 15, 3, 3, 15, 15]);
+
+        array.clear_alert();
+        assert_eq!(array.adjusted_row(1).unwrap_or_default(), [3; NUM_COLS]);
+    }
+
+    #[test]
+    fn test_set_alert_transformed() {
+        let mut array = mock_array();
+        array.transform = Transform {
+            rotation: Rotation::Deg90,
+            ..Transform::default()
+        };
+        array.fill_rect(0, 0, 2, 2, 15);
+        let drawn = array.array;
+        array.clear();
+
+        // the alert covers the same cells as drawing the same rectangle
+        array.set_alert(0, 0, 2, 2, 0);
+        for (r, row) in drawn.iter().enumerate() {
+            assert_eq!(array.adjusted_row(r).unwrap_or_default(), *row);
+        }
+
+        // and a huge one just covers everything
+        array.set_alert(i32::MAX, 0, usize::MAX, 1, 0);
+        assert!(array.shows_nothing());
+        array.set_alert(-5, -5, usize::MAX, usize::MAX, 0);
+        assert_eq!(array.adjusted_row(7).unwrap_or_default(), [15; NUM_COLS]);
+
+        // an alert over a blank frame still goes out with skip_blank
+        array.skip_blank = true;
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.written.len(), NUM_ROWS * LAYER_BITS * SPI_BYTES);
+    }
+
+    #[test]
+    fn test_keepalive() {
+        let mut array = mock_array();
+        array.keepalive().unwrap_or(());
+        assert_eq!(array.spi.written, [0xff, 0xff]);
+
+        array.array[NUM_ROWS - 1][0] = 8;
+        array.scan(1).unwrap_or(());
+        let frame = array.array;
+        let last = array.spi.written.len() - SPI_BYTES;
+        let last_layer = [array.spi.written[last], array.spi.written[last + 1]];
+        assert_eq!(last_layer, [0xff, 0xfe]);
+
+        array.spi.written.clear();
+        let row_cycles = array.row_pins.0.cycles;
+        array.keepalive().unwrap_or(());
+        assert_eq!(array.spi.written, last_layer);
+        assert_eq!(array.row_pins.0.cycles, row_cycles);
+        assert_eq!(array.array, frame);
+    }
+
+    #[test]
+    fn test_reset_registers() {
+        let mut array = mock_array();
         array.reg_pin.set_high().unwrap();
-        array.write_layer(&[0x57, 0x3f], None).unwrap_or(());
-        assert_eq!(array.spi.written, [0xa8, 0xc0]);
-        assert_eq!(array.timer.tries, 0);
+
+        array.reset_registers().unwrap_or(());
+        assert_eq!(array.spi.written, [0xff, 0xff]);
         assert_eq!(array.reg_pin.cycles, 1);
+        assert!(array.reg_pin.state);
         assert_eq!(array.output_disable.cycles, 0);
-        assert_eq!(array.output_disable.state, false);
+        assert!(!array.output_disable.state);
+    }
 
-        array.write_layer(&[13], Some(3)).unwrap_or(());
-        assert_eq!(array.reg_pin.cycles, 2);
+    #[test]
+    fn test_step_matches_scan() {
+        let mut scanned = mock_array();
+        scanned.array[2][5] = 9;
+        scanned.scan(1).unwrap_or(());
+
+        let mut stepped = mock_array();
+        stepped.array[2][5] = 9;
+        let mut steps = 0;
+        while !stepped.step(1).unwrap_or(true) {
+            steps += 1;
+        }
+
+        assert_eq!(steps + 1, NUM_ROWS * LAYER_BITS);
+        assert_eq!(stepped.spi.written, scanned.spi.written);
+        assert_eq!(stepped.frame_count, 1);
+    }
+
+    #[test]
+    fn test_scan_with_scratch() {
+        let mut scanned = mock_array();
+        scanned.array[4][11] = 6;
+        scanned.scan(1).unwrap_or(());
+
+        let mut scratched = mock_array();
+        scratched.array[4][11] = 6;
+        // leftovers in the scratch buffer mustn't leak into the output
+        let mut scratch = [[0xa5; SPI_BYTES]; LAYER_BITS];
+        scratched.scan_with_scratch(1, &mut scratch).unwrap_or(());
+
+        assert_eq!(scratched.spi.written, scanned.spi.written);
+        assert_eq!(scratched.frame_count, 1);
+    }
+
+    #[test]
+    fn test_refresh_budget() {
+        let mut scanned = mock_array();
+        scanned.array[6][1] = 11;
+        scanned.scan(1).unwrap_or(());
+
+        let mut budgeted = mock_array();
+        budgeted.array[6][1] = 11;
+        let mut calls = 1;
+        while !budgeted.refresh_budget(1, 3).unwrap_or(true) {
+            calls += 1;
+            assert!(budgeted.spi.written.len() <= calls * 3 * SPI_BYTES);
+        }
+
+        assert_eq!(calls, (NUM_ROWS * LAYER_BITS).div_ceil(3));
+        assert_eq!(budgeted.spi.written, scanned.spi.written);
+        assert_eq!(budgeted.frame_count, 1);
+    }
+
+    #[test]
+    fn test_frame_complete() {
+        let mut array = mock_array();
+        assert!(!array.take_frame_complete());
+
+        array.scan(1).unwrap_or(());
+        assert!(array.take_frame_complete());
+        assert!(!array.take_frame_complete());
+
+        // only the step that finishes the frame sets it
+        assert!(!array.step(1).unwrap_or(true));
+        assert!(!array.take_frame_complete());
+        while !array.step(1).unwrap_or(true) {}
+        assert!(array.take_frame_complete());
+    }
+
+    #[test]
+    fn test_step_interleaved_drawing() {
+        let mut array = mock_array();
+
+        // the "interrupt" shows the first layer of row 0, then the "main loop"
+        // lights every pixel in rows 0 and 1
+        array.step(1).unwrap_or(false);
+        array.array[0] = [15; NUM_COLS];
+        array.array[1] = [15; NUM_COLS];
+        for _ in 1..2 * LAYER_BITS {
+            array.step(1).unwrap_or(false);
+        }
+
+        let rows: heapless::Vec<&[u8], 2> =
+            array.spi.written.chunks(SPI_BYTES * LAYER_BITS).collect();
+        // row 0 was already prepared, so it finishes dark
+        assert!(rows[0].iter().all(|&b| b == 0xff));
+        // row 1 picks up the change
+        assert!(rows[1].iter().all(|&b| b == 0x00));
+    }
+
+    #[test]
+    fn test_invert_data() {
+        let mut array = mock_array();
+
+        array.write_layer(&[0x57, 0x3f], None).unwrap_or(());
+        assert_eq!(array.spi.written, [0xa8, 0xc0]);
+
+        array.spi.written.clear();
+        array.invert_data = false;
+        array.write_layer(&[0x57, 0x3f], None).unwrap_or(());
+        assert_eq!(array.spi.written, [0x57, 0x3f]);
+
+        array.spi.written.clear();
+        array.reset_registers().unwrap_or(());
+        assert_eq!(array.spi.written, [0x00, 0x00]);
+    }
+
+    #[test]
+    fn test_bit_depth() {
+        let mut array = mock_array();
+        array.array = [[8; NUM_COLS]; NUM_ROWS];
+
+        array.set_bit_depth(1);
+        array.scan(1).unwrap_or(());
+        // only the 8s layer is sent
+        assert_eq!(array.spi.written.len(), NUM_ROWS * SPI_BYTES);
+        assert!(array.spi.written.iter().all(|&b| b == 0x00));
+
+        array.spi.written.clear();
+        array.set_bit_depth(4);
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.written.len(), NUM_ROWS * LAYER_BITS * SPI_BYTES);
+
+        array.set_bit_depth(0);
+        assert_eq!(array.bit_depth(), 1);
+        array.set_bit_depth(9);
+        assert_eq!(array.bit_depth(), 4);
+    }
+
+    #[test]
+    fn test_prepare_dma_buffer() {
+        let mut array = mock_array();
+        array.array[0] = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0];
+        array.array[7][0] = 5;
+
+        let mut buf = [0u8; DMA_BUFFER_LEN];
+        array.prepare_dma_buffer(&mut buf).unwrap();
+
+        #[rustfmt::skip]
+        let row_0 = [
+            0b10101010, 0b10101010,
+            0b11001100, 0b11001100,
+            0b11110000, 0b11110000,
+            0b11111111, 0b00000000,
+        ];
+        assert_eq!(buf[..8], row_0);
+        assert!(buf[8..56].iter().all(|&b| b == 0xff));
+        assert_eq!(buf[56..], [0xff, 0xfe, 0xff, 0xff, 0xff, 0xfe, 0xff, 0xff]);
+
+        // a frame scanned normally sends the same bytes
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.written, buf);
+    }
+
+    #[test]
+    fn test_prepare_frame() {
+        let mut array = mock_array();
+        for (r, row) in array.array.iter_mut().enumerate() {
+            for (c, brightness) in row.iter_mut().enumerate() {
+                *brightness = if r < 4 { c as u8 } else { 15 - c as u8 };
+            }
+        }
+
+        let mut out = [[[0u8; SPI_BYTES]; LAYER_BITS]; NUM_ROWS];
+        array.prepare_frame(&mut out).unwrap();
+
+        #[rustfmt::skip]
+        let rising = [
+            [0b10101010, 0b10101010],
+            [0b11001100, 0b11001100],
+            [0b11110000, 0b11110000],
+            [0b11111111, 0b00000000],
+        ];
+        #[rustfmt::skip]
+        let falling = [
+            [0b01010101, 0b01010101],
+            [0b00110011, 0b00110011],
+            [0b00001111, 0b00001111],
+            [0b00000000, 0b11111111],
+        ];
+        assert!(out[..4].iter().all(|layers| *layers == rising));
+        assert!(out[4..].iter().all(|layers| *layers == falling));
+    }
+
+    #[test]
+    fn test_skip_empty_layers() {
+        let mut array = mock_array();
+        array.skip_empty_layers = true;
+        array.array[2][5] = 1;
+
+        array.scan(1).unwrap_or(());
+        // only the lowest layer of row 2 has anything lit
+        assert_eq!(array.spi.transfers, 1);
+        assert_eq!(array.spi.written, [0xff, 0xdf]);
+
+        array.skip_empty_layers = false;
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.transfers, 1 + NUM_ROWS * LAYER_BITS);
+    }
+
+    #[test]
+    fn test_set_inverted() {
+        let mut array = mock_array();
+        array.array[0][0] = 15;
+        array.array[0][1] = 5;
+        array.set_inverted(true);
+        assert!(array.is_inverted());
+
+        let mut layers = [[0u8; SPI_BYTES]; LAYER_BITS];
+        array.prepare_row(0, &mut layers).unwrap();
+        // column 0 is off, column 1 is 10 and the rest are full
+        assert_eq!(
+            layers,
+            [
+                [0xff, 0b11111100],
+                [0xff, 0b11111110],
+                [0xff, 0b11111100],
+                [0xff, 0b11111110],
+            ]
+        );
+
+        array.prepare_row(1, &mut layers).unwrap();
+        assert_eq!(layers, [[0xff; SPI_BYTES]; LAYER_BITS]);
+    }
+
+    #[test]
+    fn test_layer_bytes() {
+        let mut array = mock_array();
+        array.array[3] = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0];
+
+        assert_eq!(array.layer_bytes(3, 0), [0b10101010, 0b10101010]);
+        assert_eq!(array.layer_bytes(3, 3), [0b11111111, 0b00000000]);
+        assert_eq!(array.layer_bytes(NUM_ROWS, 0), [0xff, 0xff]);
+        assert_eq!(array.layer_bytes(3, LAYER_BITS), [0xff, 0xff]);
+
+        array.invert_data = false;
+        assert_eq!(array.layer_bytes(3, 3), [0b00000000, 0b11111111]);
+
+        // and they are what a scan sends
+        array.invert_data = true;
+        array.scan(1).unwrap_or(());
+        let sent = &array.spi.written[3 * LAYER_BITS * SPI_BYTES..][..SPI_BYTES];
+        assert_eq!(sent, array.layer_bytes(3, 0));
+    }
+
+    #[test]
+    fn test_spi_chunk() {
+        let mut whole = mock_array();
+        whole.array[3] = [15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0];
+        whole.scan(1).unwrap_or(());
+        assert_eq!(whole.spi.transfers, NUM_ROWS * LAYER_BITS);
+
+        let mut chunked = mock_array();
+        chunked.array = whole.array;
+        chunked.spi_chunk = 1;
+        chunked.scan(1).unwrap_or(());
+        assert_eq!(chunked.spi.transfers, NUM_ROWS * LAYER_BITS * SPI_BYTES);
+
+        assert_eq!(chunked.spi.written, whole.spi.written);
+    }
+
+    #[test]
+    fn test_timer_stuck() {
+        let mut array = mock_array();
+        array.max_timer_spins = Some(10);
+
+        // a timer that won't expire within the limit
+        array.timer.tries = i32::MAX;
+        assert!(matches!(
+            array.write_layer(&[0, 0], Some(1)),
+            Err(LEDError::TimerStuck)
+        ));
+        assert_eq!(array.timer.tries, i32::MAX - 11);
+
+        // one that expires just in time
+        array.timer.tries = 10;
+        assert!(array.write_layer(&[0, 0], Some(1)).is_ok());
+    }
+
+    #[test]
+    fn test_row_brightness() {
+        let mut array = mock_array();
+        array.array = [[14; NUM_COLS]; NUM_ROWS];
+        array.row_brightness[3] = 128;
+
+        array.scan(1).unwrap_or(());
+
+        let rows = scanned_brightness(&array.spi.written);
+        assert_eq!(rows, [14, 14, 14, 7, 14, 14, 14, 14]);
+    }
+
+    #[test]
+    fn test_scan_traced() {
+        let mut array = mock_array();
+        let mut trace = MockPin::new();
+
+        array.scan_traced(1, &mut trace).unwrap_or(());
+        assert_eq!(trace.cycles, NUM_ROWS as u32 + 1);
+        assert!(!trace.state);
+
+        array.scan_traced(1, &mut trace).unwrap_or(());
+        assert_eq!(trace.cycles, 2 * (NUM_ROWS as u32 + 1));
+    }
+
+    #[test]
+    fn test_is_blank() {
+        let mut array = mock_array();
+        assert!(array.is_blank());
+
+        array.set_pixel(5, 9, 1);
+        assert!(!array.is_blank());
+    }
+
+    #[test]
+    fn test_skip_blank() {
+        let mut array = mock_array();
+        array.skip_blank = true;
+
+        array.scan(1).unwrap_or(());
+        assert!(array.spi.written.is_empty());
+        assert!(array.output_disable.state);
+        // skipped frames still finish
+        assert_eq!(array.frame_count, 1);
+        assert!(array.take_frame_complete());
+
+        // an inverted blank array is fully lit
+        array.set_inverted(true);
+        array.spi.written.clear();
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.written.len(), NUM_ROWS * LAYER_BITS * SPI_BYTES);
+        assert!(!array.output_disable.state);
+        array.set_inverted(false);
+
+        array.set_pixel(5, 9, 1);
+        array.spi.written.clear();
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.written.len(), NUM_ROWS * LAYER_BITS * SPI_BYTES);
+        assert!(!array.output_disable.state);
+    }
+
+    #[test]
+    fn test_skip_unchanged() {
+        let mut array = mock_array();
+        array.skip_unchanged = true;
+        array.set_pixel(2, 3, 5);
+
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.written.len(), NUM_ROWS * LAYER_BITS * SPI_BYTES);
+
+        array.spi.written.clear();
+        array.scan(1).unwrap_or(());
+        assert!(array.spi.written.is_empty());
+
+        // changing one pixel's 2s bit only resends that layer of that row
+        array.set_pixel(2, 3, 7);
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.written, [0xff, !(1 << 3)]);
+    }
+
+    #[test]
+    fn test_skip_unchanged_cache() {
+        let full = NUM_ROWS * LAYER_BITS * SPI_BYTES;
+        let mut array = mock_array();
+        array.skip_unchanged = true;
+        array.set_pixel(2, 3, 5);
+        array.scan(1).unwrap_or(());
+
+        // a frame scanned with the flag off is still remembered
+        array.skip_unchanged = false;
+        array.set_pixel(2, 3, 9);
+        array.scan(1).unwrap_or(());
+        array.skip_unchanged = true;
+        array.spi.written.clear();
+        array.scan(1).unwrap_or(());
+        assert!(array.spi.written.is_empty());
+
+        // writing to the driver any other way sends everything again
+        for case in 0..5 {
+            match case {
+                0 => array.reset_registers().unwrap_or(()),
+                1 => array.keepalive().unwrap_or(()),
+                2 => array.write_layer(&[0, 0], Some(0)).unwrap_or(()),
+                3 => array.set_inverted(false),
+                _ => {
+                    array.spi.loopback = true;
+                    array.diagnose().unwrap_or_default();
+                    array.spi.loopback = false;
+                }
+            }
+            array.spi.written.clear();
+            array.scan(1).unwrap_or(());
+            assert_eq!(array.spi.written.len(), full, "case {}", case);
+
+            array.spi.written.clear();
+            array.scan(1).unwrap_or(());
+            assert!(array.spi.written.is_empty(), "case {}", case);
+        }
+    }
+
+    #[test]
+    fn test_skip_unchanged_switches_rows() {
+        let log = EventLog::default();
+        let mut array = logged_array(&log);
+        array.skip_unchanged = true;
+        array.scan(1).unwrap_or(());
+        log.take();
+
+        // nothing is shifted or latched, but every row is still selected
+        array.scan(1).unwrap_or(());
+        let events = log.take();
+        assert!(events.iter().all(|&(pin, _)| pin != "reg"));
+        let rows: heapless::Vec<u8, 8> = events
+            .chunks(5)
+            .map(|switch| {
+                assert_eq!(switch[0], ("od", true));
+                assert_eq!(switch[4], ("od", false));
+                switch[1..4]
+                    .iter()
+                    .enumerate()
+                    .map(|(bit, &(_, high))| (high as u8) << bit)
+                    .sum()
+            })
+            .collect();
+        assert_eq!(rows, [0, 1, 2, 3, 4, 5, 6, 7]);
+        // only the first scan sent anything
+        assert_eq!(array.spi.written.len(), NUM_ROWS * LAYER_BITS * SPI_BYTES);
+    }
+
+    #[test]
+    fn test_skip_unchanged_and_empty_layers() {
+        let mut array = mock_array();
+        array.skip_unchanged = true;
+        array.skip_empty_layers = true;
+
+        // the top three layers of the last row are empty, so they have to be
+        // blanked every frame rather than left showing the 1s layer
+        array.array[NUM_ROWS - 1][0] = 1;
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.written.len(), SPI_BYTES);
+        array.scan(1).unwrap_or(());
+        assert_eq!(array.spi.written.len(), SPI_BYTES);
+        assert!(array.output_disable.state);
+
+        // an unchanged layer after an empty one comes back on
+        array.array[NUM_ROWS - 1][0] = 0b1101;
+        array.scan(1).unwrap_or(());
+        array.spi.written.clear();
+        array.scan(1).unwrap_or(());
+        assert!(array.spi.written.is_empty());
+        assert!(!array.output_disable.state);
+    }
+
+    /// A panel whose control pins all log to `log`.
+    fn logged_array(
+        log: &EventLog,
+    ) -> LEDArray<LogPin<'_>, LogPin<'_>, LogPin<'_>, MockTimer, MockSPI, LogPin<'_>, LogPin<'_>>
+    {
+        LEDArray::new(
+            (
+                LogPin::new("row0", log),
+                LogPin::new("row1", log),
+                LogPin::new("row2", log),
+            ),
+            MockTimer { tries: 0 },
+            MockSPI::new(),
+            LogPin::new("reg", log),
+            LogPin::new("od", log),
+        )
+    }
+
+    #[test]
+    fn test_row_switch_order() {
+        let log = EventLog::default();
+        let mut array = logged_array(&log);
+        array.write_layer(&[0, 0], Some(1)).unwrap_or(());
+        assert_eq!(
+            log.take(),
+            [
+                ("reg", false),
+                ("od", true),
+                ("row0", true),
+                ("row1", false),
+                ("row2", false),
+                ("reg", true),
+                ("od", false),
+            ]
+        );
+
+        array.fast_row_switch = true;
+        array.write_layer(&[0, 0], Some(2)).unwrap_or(());
+        assert_eq!(
+            log.take(),
+            [
+                ("reg", false),
+                ("reg", true),
+                ("od", true),
+                ("row0", false),
+                ("row1", true),
+                ("row2", false),
+                ("od", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_layer_segmented() {
+        let log = EventLog::default();
+        let mut array = LEDArray::new(
+            (MockPin::new(), MockPin::new(), MockPin::new()),
+            MockTimer { tries: 0 },
+            LogSPI::new(&log),
+            MockPin::new(),
+            MockPin::new(),
+        );
+        array.invert_data = false;
+        let mut latches = [LogPin::new("seg0", &log), LogPin::new("seg1", &log)];
+
+        array
+            .write_layer_segmented(&[0x12, 0x34], None, &mut latches)
+            .unwrap_or(());
+
+        assert_eq!(
+            log.take(),
+            [
+                ("seg0", false),
+                ("spi", true),
+                ("seg0", true),
+                ("seg1", false),
+                ("spi", true),
+                ("seg1", true),
+            ]
+        );
+        assert_eq!(array.spi.written, [0x12, 0x34]);
+        assert!(!array.reg_pin.state);
+
+        // switching rows blanks around all of the segments
+        array
+            .write_layer_segmented(&[0x56, 0x78], Some(3), &mut latches)
+            .unwrap_or(());
         assert_eq!(array.output_disable.cycles, 1);
-        assert_eq!(array.output_disable.state, false);
-        assert_eq!(array.row_pins.2.state, false);
-        assert_eq!(array.row_pins.1.state, true);
-        assert_eq!(array.row_pins.0.state, true);
+        assert_eq!(log.take().len(), 6);
+
+        // keepalive resends the segmented layer, not an older one
+        array.spi.written.clear();
+        array.keepalive().unwrap_or(());
+        assert_eq!(array.spi.written, [0x56, 0x78]);
+
+        // the latches are held like `reg_pin`
+        array.latch_pulse_ticks = 2;
+        array
+            .write_layer_segmented(&[0, 0], None, &mut latches)
+            .unwrap_or(());
+        let events = log.take();
+        assert_eq!(events.iter().filter(|&&e| e == ("seg1", true)).count(), 3);
+
+        // the first piece goes out before the wait
+        array.spi.written.clear();
+        array.timer.tries = i32::MAX;
+        array.max_timer_spins = Some(1);
+        assert!(matches!(
+            array.write_layer_segmented(&[0x9a, 0xbc], None, &mut latches),
+            Err(LEDError::TimerStuck)
+        ));
+        assert_eq!(array.spi.written, [0x9a]);
+        log.take();
+
+        // more latches than pieces, or none, are turned down
+        let mut three = [
+            LogPin::new("seg0", &log),
+            LogPin::new("seg1", &log),
+            LogPin::new("seg2", &log),
+        ];
+        array.timer.tries = 0;
+        array.spi.written.clear();
+        assert!(matches!(
+            array.write_layer_segmented(&[0, 0], None, &mut three),
+            Err(LEDError::LatchCount(3))
+        ));
+        let mut none: [LogPin; 0] = [];
+        assert!(matches!(
+            array.write_layer_segmented(&[0, 0], None, &mut none),
+            Err(LEDError::LatchCount(0))
+        ));
+        assert!(array.spi.written.is_empty());
+        assert!(log.take().is_empty());
+    }
+
+    #[test]
+    fn test_ensure_blanked() {
+        let log = EventLog::default();
+        let mut array = logged_array(&log);
+
+        array.ensure_blanked().unwrap_or(());
+        array.ensure_blanked().unwrap_or(());
+        assert_eq!(log.take(), [("od", true)]);
+
+        // switching rows while already blanked only has to lower it
+        array.write_layer(&[0, 0], Some(0)).unwrap_or(());
+        let events = log.take();
+        assert_eq!(events.iter().filter(|e| e.0 == "od").count(), 1);
+        assert_eq!(events.last(), Some(&("od", false)));
+
+        array.unblank().unwrap_or(());
+        array.ensure_blanked().unwrap_or(());
+        assert_eq!(log.take(), [("od", false), ("od", true)]);
+    }
+
+    #[test]
+    fn test_spi_bytes() {
+        assert_eq!(mock_array().spi_bytes(), 2);
+    }
+
+    #[test]
+    fn test_error_code() {
+        let errors: [LEDError<(), ()>; 8] = [
+            LEDError::PinError(()),
+            LEDError::SPIError(()),
+            LEDError::LengthMismatch {
+                expected: 2,
+                got: 3,
+            },
+            LEDError::InvalidRow(9),
+            LEDError::TimerStuck,
+            LEDError::NoBaseFreq,
+            LEDError::SpiTimeout,
+            LEDError::LatchCount(3),
+        ];
+        let codes: heapless::Vec<u8, 8> = errors.iter().map(LEDError::code).collect();
+        assert_eq!(codes, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_check_spi_mode() {
+        assert!(check_spi_mode(hal::spi::MODE_0));
+        assert!(!check_spi_mode(hal::spi::MODE_1));
+        assert!(!check_spi_mode(hal::spi::MODE_3));
+    }
+
+    #[test]
+    fn test_latch_pulse_ticks() {
+        let log = EventLog::default();
+        let mut array = logged_array(&log);
+        array.latch_pulse_ticks = 2;
+
+        array.write_layer(&[0, 0], None).unwrap_or(());
+        assert_eq!(
+            log.take(),
+            [("reg", false), ("reg", true), ("reg", true), ("reg", true)]
+        );
+
+        // the latch is held before the output comes back on
+        array.write_layer(&[0, 0], Some(0)).unwrap_or(());
+        let events = log.take();
+        assert_eq!(
+            events[events.len() - 4..],
+            [("reg", true), ("reg", true), ("reg", true), ("od", false),]
+        );
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut array = recording_array();
+        assert!(matches!(array.refresh(), Err(LEDError::NoBaseFreq)));
+
+        array.set_base_freq(100);
+        array.refresh().unwrap();
+        assert_eq!(array.timer.started[..LAYER_BITS], [800, 400, 200, 100]);
+
+        array.timer.started.clear();
+        array.set_base_freq(25);
+        array.refresh().unwrap();
+        assert_eq!(array.timer.started[..LAYER_BITS], [200, 100, 50, 25]);
+        assert_eq!(array.frame_count, 2);
+    }
+
+    #[cfg(feature = "defmt")]
+    #[test]
+    fn test_defmt_format() {
+        use core::sync::atomic::Ordering;
+
+        let error: LEDError<(), ()> = LEDError::LengthMismatch {
+            expected: SPI_BYTES,
+            got: 0,
+        };
+        let before = LOGGED_BYTES.load(Ordering::SeqCst);
+        defmt::println!("{} {}", error, InvalidRow(NUM_ROWS));
+        defmt::println!("{}", Frame([[0; NUM_COLS]; NUM_ROWS]));
+        assert!(LOGGED_BYTES.load(Ordering::SeqCst) > before);
     }
 }
@@ -0,0 +1,133 @@
+use crate::fonts::{Font, Font5x7};
+use crate::LEDArray;
+
+/// Text that scrolls right to left across the drawing area in a continuous
+/// loop.
+///
+/// The text is treated as a ring: as the end scrolls past, the start follows
+/// straight after it, separated by the usual gap between glyphs.
+pub struct Marquee<'a> {
+    pub text: &'a str,
+    pub brightness: u8,
+    /// How many columns the text has scrolled by.
+    pub position: usize,
+}
+
+impl<'a> Marquee<'a> {
+    pub fn new(text: &'a str, brightness: u8) -> Self {
+        Marquee {
+            text,
+            brightness,
+            position: 0,
+        }
+    }
+
+    /// The width of one full loop of the text, in columns.
+    pub fn width(&self) -> usize {
+        self.text.chars().count() * Font5x7.advance()
+    }
+
+    /// Draw the visible window of text over the whole array, then scroll one
+    /// column. Does nothing while the array is paused.
+    pub fn render<R0, R1, R2, Timer, SPI, Reg, OD>(
+        &mut self,
+        array: &mut LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>,
+    ) {
+        if array.is_paused() {
+            return;
+        }
+
+        array.clear();
+
+        let width = self.width();
+        if width == 0 {
+            return;
+        }
+
+        // draw as many copies as it takes to cover the drawing area
+        let columns = array.transform.size().0 as i32;
+        let mut x = -(self.position as i32);
+        while x < columns {
+            x = array.draw_str(x, 0, self.text, self.brightness);
+        }
+
+        self.position = (self.position + 1) % width;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_array;
+    use crate::{Rotation, NUM_COLS, NUM_ROWS};
+
+    /// The columns lit in row 3, where only the stem of an `I` is drawn.
+    fn lit_columns<R0, R1, R2, Timer, SPI, Reg, OD>(
+        array: &LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>,
+    ) -> heapless::Vec<usize, 16> {
+        (0..NUM_COLS).filter(|&c| array.array[3][c] != 0).collect()
+    }
+
+    #[test]
+    fn test_marquee_scrolls_and_wraps() {
+        let mut array = mock_array();
+        let mut marquee = Marquee::new("III", 9);
+        assert_eq!(marquee.width(), 18);
+
+        marquee.render(&mut array);
+        assert_eq!(lit_columns(&array), [2, 8, 14]);
+
+        marquee.render(&mut array);
+        assert_eq!(lit_columns(&array), [1, 7, 13]);
+
+        marquee.render(&mut array);
+        marquee.render(&mut array);
+        marquee.render(&mut array);
+        assert_eq!(lit_columns(&array), [4, 10]);
+        // the first I has scrolled off, and the loop brings it back on the right
+        marquee.render(&mut array);
+        assert_eq!(lit_columns(&array), [3, 9, 15]);
+
+        while marquee.position != 0 {
+            marquee.render(&mut array);
+        }
+        marquee.render(&mut array);
+        assert_eq!(lit_columns(&array), [2, 8, 14]);
+    }
+
+    #[test]
+    fn test_marquee_transformed() {
+        let mut array = mock_array();
+        array.transform.rotation = Rotation::Deg90;
+        let mut marquee = Marquee::new("III", 9);
+
+        // turned a quarter, text row 3 is panel column 12 and only the 8
+        // columns of the drawing area are covered
+        let lit_rows = |array: &LEDArray<_, _, _, _, _, _, _>| {
+            (0..NUM_ROWS)
+                .filter(|&r| array.array[r][12] != 0)
+                .collect::<heapless::Vec<usize, 8>>()
+        };
+        marquee.render(&mut array);
+        assert_eq!(lit_rows(&array), [2]);
+        marquee.render(&mut array);
+        assert_eq!(lit_rows(&array), [1, 7]);
+    }
+
+    #[test]
+    fn test_marquee_paused() {
+        let mut array = mock_array();
+        let mut marquee = Marquee::new("III", 9);
+
+        marquee.render(&mut array);
+        array.pause();
+        marquee.render(&mut array);
+        marquee.render(&mut array);
+        assert_eq!(lit_columns(&array), [2, 8, 14]);
+        assert_eq!(marquee.position, 1);
+
+        array.resume();
+        marquee.render(&mut array);
+        assert_eq!(lit_columns(&array), [1, 7, 13]);
+    }
+}
@@ -0,0 +1,86 @@
+//! Integer helpers for smooth effects, so they don't need floats or `libm`.
+
+/// One full period of a sine wave in 256 steps, as `128 + 127 * sin`, rounded.
+#[rustfmt::skip]
+pub const SINE_256: [u8; 256] = [
+    128, 131, 134, 137, 140, 144, 147, 150, 153, 156, 159, 162, 165, 168, 171, 174,
+    177, 179, 182, 185, 188, 191, 193, 196, 199, 201, 204, 206, 209, 211, 213, 216,
+    218, 220, 222, 224, 226, 228, 230, 232, 234, 235, 237, 239, 240, 241, 243, 244,
+    245, 246, 248, 249, 250, 250, 251, 252, 253, 253, 254, 254, 254, 255, 255, 255,
+    255, 255, 255, 255, 254, 254, 254, 253, 253, 252, 251, 250, 250, 249, 248, 246,
+    245, 244, 243, 241, 240, 239, 237, 235, 234, 232, 230, 228, 226, 224, 222, 220,
+    218, 216, 213, 211, 209, 206, 204, 201, 199, 196, 193, 191, 188, 185, 182, 179,
+    177, 174, 171, 168, 165, 162, 159, 156, 153, 150, 147, 144, 140, 137, 134, 131,
+    128, 125, 122, 119, 116, 112, 109, 106, 103, 100, 97, 94, 91, 88, 85, 82,
+    79, 77, 74, 71, 68, 65, 63, 60, 57, 55, 52, 50, 47, 45, 43, 40,
+    38, 36, 34, 32, 30, 28, 26, 24, 22, 21, 19, 17, 16, 15, 13, 12,
+    11, 10, 8, 7, 6, 6, 5, 4, 3, 3, 2, 2, 2, 1, 1, 1,
+    1, 1, 1, 1, 2, 2, 2, 3, 3, 4, 5, 6, 6, 7, 8, 10,
+    11, 12, 13, 15, 16, 17, 19, 21, 22, 24, 26, 28, 30, 32, 34, 36,
+    38, 40, 43, 45, 47, 50, 52, 55, 57, 60, 63, 65, 68, 71, 74, 77,
+    79, 82, 85, 88, 91, 94, 97, 100, 103, 106, 109, 112, 116, 119, 122, 125,
+];
+
+/// The sine of `phase`, where a full turn is 256, scaled to 1..=255 with 128
+/// as zero.
+pub fn sin_u8(phase: u8) -> u8 {
+    SINE_256[phase as usize]
+}
+
+/// The angle of the point `(x, y)` from the positive `x` axis toward the
+/// positive `y` axis, where a full turn is 256, like `atan2`. Accurate to
+/// within a step. The origin gives 0.
+pub fn atan2_u8(y: i32, x: i32) -> u8 {
+    if x == 0 && y == 0 {
+        return 0;
+    }
+
+    // atan(t) for t in 0..=1 as t / 256, in 256ths of a turn: the straight
+    // line from 0 to an eighth of a turn, plus a parabola for its bow
+    let octant = |t: u32| ((t * 32 + t * (256 - t) * 11 / 256 + 128) >> 8) as u8;
+
+    let (ax, ay) = (x.unsigned_abs(), y.unsigned_abs());
+    let angle = if ax >= ay {
+        octant((ay as u64 * 256 / ax as u64) as u32)
+    } else {
+        64 - octant((ax as u64 * 256 / ay as u64) as u32)
+    };
+
+    match (x >= 0, y >= 0) {
+        (true, true) => angle,
+        (false, true) => 128 - angle,
+        (false, false) => 128 + angle,
+        (true, false) => angle.wrapping_neg(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sin_u8() {
+        assert_eq!(sin_u8(0), 128);
+        assert_eq!(sin_u8(64), 255);
+        assert_eq!(sin_u8(128), 128);
+        assert_eq!(sin_u8(192), 1);
+        // sin(45 degrees) is about 0.707
+        assert_eq!(sin_u8(32), 218);
+        assert_eq!(sin_u8(224), 38);
+    }
+
+    #[test]
+    fn test_atan2_u8() {
+        assert_eq!(atan2_u8(0, 1), 0);
+        assert_eq!(atan2_u8(1, 1), 32);
+        assert_eq!(atan2_u8(1, 0), 64);
+        assert_eq!(atan2_u8(1, -1), 96);
+        assert_eq!(atan2_u8(0, -1), 128);
+        assert_eq!(atan2_u8(-1, 0), 192);
+        assert_eq!(atan2_u8(-1, 1), 224);
+        assert_eq!(atan2_u8(0, 0), 0);
+        // 30 degrees is 21.3 steps
+        assert_eq!(atan2_u8(577, 1000), 21);
+        assert_eq!(atan2_u8(i32::MIN, 0), 192);
+    }
+}
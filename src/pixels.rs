@@ -0,0 +1,98 @@
+use core::ops::{Index, IndexMut};
+
+use crate::{LEDArray, NUM_COLS, NUM_ROWS};
+
+/// A bounds checked view of the framebuffer, indexed by `(row, col)`.
+///
+/// `get` and `get_mut` return `None` off the panel. Indexing off the panel is
+/// caught by a `debug_assert!`; in release builds it reads 0 and writes go
+/// nowhere, so it can never panic in firmware.
+pub struct Pixels<'a> {
+    array: &'a mut [[u8; NUM_COLS]; NUM_ROWS],
+    // where out of bounds writes go in release builds
+    sink: u8,
+}
+
+impl<'a> Pixels<'a> {
+    pub fn get(&self, row: usize, col: usize) -> Option<&u8> {
+        self.array.get(row).and_then(|r| r.get(col))
+    }
+
+    pub fn get_mut(&mut self, row: usize, col: usize) -> Option<&mut u8> {
+        self.array.get_mut(row).and_then(|r| r.get_mut(col))
+    }
+}
+
+impl<'a> Index<(usize, usize)> for Pixels<'a> {
+    type Output = u8;
+
+    fn index(&self, (row, col): (usize, usize)) -> &u8 {
+        debug_assert!(
+            row < NUM_ROWS && col < NUM_COLS,
+            "pixel ({}, {}) is off the panel",
+            row,
+            col
+        );
+        self.get(row, col).unwrap_or(&0)
+    }
+}
+
+impl<'a> IndexMut<(usize, usize)> for Pixels<'a> {
+    fn index_mut(&mut self, (row, col): (usize, usize)) -> &mut u8 {
+        debug_assert!(
+            row < NUM_ROWS && col < NUM_COLS,
+            "pixel ({}, {}) is off the panel",
+            row,
+            col
+        );
+        match self.array.get_mut(row).and_then(|r| r.get_mut(col)) {
+            Some(cell) => cell,
+            None => &mut self.sink,
+        }
+    }
+}
+
+impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    /// A bounds checked view of `array`. Counts as a change to `generation`
+    /// whether or not anything is written through it.
+    pub fn pixels(&mut self) -> Pixels<'_> {
+        self.touch();
+        Pixels {
+            array: &mut self.array,
+            sink: 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_array;
+
+    #[test]
+    fn test_pixels() {
+        let mut array = mock_array();
+        let mut pixels = array.pixels();
+
+        assert_eq!(pixels.get(NUM_ROWS, 0), None);
+        assert_eq!(pixels.get(0, NUM_COLS), None);
+        assert_eq!(pixels.get_mut(NUM_ROWS, NUM_COLS), None);
+
+        *pixels.get_mut(1, 2).unwrap() = 3;
+        pixels[(7, 15)] = 9;
+        assert_eq!(pixels[(1, 2)], 3);
+        assert_eq!(pixels.get(7, 15), Some(&9));
+
+        assert_eq!(array.array[1][2], 3);
+        assert_eq!(array.array[7][15], 9);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_pixels_index_out_of_bounds() {
+        let mut array = mock_array();
+        let pixels = array.pixels();
+        let _ = pixels[(NUM_ROWS, 0)];
+    }
+}
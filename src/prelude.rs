@@ -0,0 +1,35 @@
+//! The types and traits most programs need, for importing in one go:
+//!
+//! ```
+//! use matrix_display::prelude::*;
+//! ```
+
+pub use crate::fonts::{Font, Font3x5, Font5x7, Font8x8};
+pub use crate::{
+    BlendMode, Breathe, ColorOrder, Cursor, Direction, Frame, FrameSource, GrayImage, LEDArray,
+    LEDError, Marquee, Rotation, StatusLine, Transform,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock::*;
+
+    #[test]
+    fn test_prelude() {
+        let mut array: LEDArray<MockPin, MockPin, MockPin, MockTimer, MockSPI, MockPin, MockPin> =
+            LEDArray::new(
+                (MockPin::new(), MockPin::new(), MockPin::new()),
+                MockTimer { tries: 0 },
+                MockSPI::new(),
+                MockPin::new(),
+                MockPin::new(),
+            );
+
+        array.draw_char_with(&Font3x5, 0, 0, 'A', 5);
+        array.blend(&Frame([[1; 16]; 8]), BlendMode::Max);
+        array.gradient_fill(0, 3, Direction::Vertical);
+        let result: Result<(), LEDError<(), ()>> = array.scan(1);
+        assert!(result.is_ok());
+    }
+}
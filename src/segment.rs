@@ -0,0 +1,148 @@
+use core::convert::TryFrom;
+
+use crate::LEDArray;
+
+/// Lit segments for each digit, with segment `a` in bit 0 through `g` in bit 6.
+///
+/// ```text
+///  aaa
+/// f   b
+/// f   b
+///  ggg
+/// e   c
+/// e   c
+///  ddd
+/// ```
+const DIGIT_SEGMENTS: [u8; 10] = [0x3f, 0x06, 0x5b, 0x4f, 0x66, 0x6d, 0x7d, 0x07, 0x7f, 0x6f];
+
+impl<R0, R1, R2, Timer, SPI, Reg, OD> LEDArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    /// Draw `digit` as a seven segment digit with its top left corner at
+    /// `(x, y)`. Each segment is `size` pixels long, so the digit takes up
+    /// `size + 2` columns and `2 * size + 3` rows. Digits above 9 are ignored.
+    pub fn draw_digit(&mut self, x: i32, y: i32, digit: u8, size: usize, brightness: u8) {
+        let segments = match DIGIT_SEGMENTS.get(digit as usize) {
+            Some(&segments) => segments,
+            None => return,
+        };
+
+        // saturating, since a digit too big for an i32 is mostly off the panel
+        let s = i32::try_from(size).unwrap_or(i32::MAX);
+        // (column, row) of the first pixel of each segment, and whether it runs
+        // across rather than down
+        let layout = [
+            (1, 0, true),
+            (s.saturating_add(1), 1, false),
+            (s.saturating_add(1), s.saturating_add(2), false),
+            (1, s.saturating_mul(2).saturating_add(2), true),
+            (0, s.saturating_add(2), false),
+            (0, 1, false),
+            (1, s.saturating_add(1), true),
+        ];
+
+        for (segment, &(dx, dy, across)) in layout.iter().enumerate() {
+            if (segments >> segment) & 1 == 0 {
+                continue;
+            }
+            if across {
+                self.fill_rect(
+                    x.saturating_add(dx),
+                    y.saturating_add(dy),
+                    size,
+                    1,
+                    brightness,
+                );
+            } else {
+                self.fill_rect(
+                    x.saturating_add(dx),
+                    y.saturating_add(dy),
+                    1,
+                    size,
+                    brightness,
+                );
+            }
+        }
+    }
+
+    /// Draw `value` in decimal as seven segment digits starting at `(x, y)`,
+    /// with one blank column between digits. Returns the `x` just past the
+    /// last digit.
+    pub fn draw_number(&mut self, x: i32, y: i32, value: u32, size: usize, brightness: u8) -> i32 {
+        let mut digits = [0u8; 10];
+        let mut count = 0;
+        let mut rest = value;
+        loop {
+            digits[count] = (rest % 10) as u8;
+            count += 1;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+
+        let advance = i32::try_from(size).unwrap_or(i32::MAX).saturating_add(3);
+        let mut x = x;
+        for &digit in digits[..count].iter().rev() {
+            self.draw_digit(x, y, digit, size, brightness);
+            x = x.saturating_add(advance);
+        }
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::mock_array;
+    use crate::{NUM_COLS, NUM_ROWS};
+
+    #[test]
+    fn test_draw_digit_eight() {
+        let mut array = mock_array();
+
+        array.draw_digit(0, 0, 8, 2, 7);
+
+        #[rustfmt::skip]
+        let expected = [
+            [0, 7, 7, 0],
+            [7, 0, 0, 7],
+            [7, 0, 0, 7],
+            [0, 7, 7, 0],
+            [7, 0, 0, 7],
+            [7, 0, 0, 7],
+            [0, 7, 7, 0],
+        ];
+        for (row, cells) in expected.iter().enumerate() {
+            assert_eq!(array.array[row][..4], cells[..]);
+        }
+        let lit = array.array.iter().flatten().filter(|&&b| b != 0).count();
+        assert_eq!(lit, 14);
+    }
+
+    #[test]
+    fn test_draw_number() {
+        let mut array = mock_array();
+
+        let end = array.draw_number(0, 0, 17, 2, 7);
+        assert_eq!(end, 10);
+
+        // the 1 is just the right hand segments, the 7 adds the top
+        assert_eq!(array.array[0][..4], [0, 0, 0, 0]);
+        assert_eq!(array.array[1][..4], [0, 0, 0, 7]);
+        assert_eq!(array.array[0][5..9], [0, 7, 7, 0]);
+        assert_eq!(array.array[4][5..9], [0, 0, 0, 7]);
+        assert_eq!(array.array[6][5..9], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_draw_number_at_the_limits() {
+        let mut array = mock_array();
+
+        array.draw_digit(i32::MAX - 1, i32::MAX, 8, 2, 7);
+        array.draw_digit(0, 0, 8, usize::MAX, 7);
+        assert_eq!(array.draw_number(i32::MAX - 3, 0, 88, 2, 7), i32::MAX);
+        assert_eq!(array.draw_number(0, 0, 88, usize::MAX, 7), i32::MAX);
+
+        // the huge digits still light their top left segments
+        assert_eq!(array.array[0][1..], [7; NUM_COLS - 1]);
+        assert_eq!(array.array[NUM_ROWS - 1][0], 7);
+    }
+}
@@ -0,0 +1,141 @@
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+
+use crate::{hal, LEDArray, LEDError};
+use hal::blocking::spi::Write;
+use hal::digital::v2::OutputPin;
+
+// the array, if one is installed yet
+type Slot<R0, R1, R2, Timer, SPI, Reg, OD> =
+    RefCell<Option<LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>>>;
+
+/// An `LEDArray` shared between an interrupt that scans it with `step` and a
+/// main loop that draws into it.
+///
+/// Every call holds a critical section for as long as it runs, so a `step`
+/// never sees half of a batch of drawing and drawing never lands partway
+/// through a layer. The interrupt can't run while `draw` holds the lock, so
+/// keep each batch short or that layer shows for longer than it should.
+///
+/// It starts empty so it can live in a `static` before the peripherals are
+/// set up; `install` puts the array in once they are.
+pub struct SharedArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    array: Mutex<Slot<R0, R1, R2, Timer, SPI, Reg, OD>>,
+}
+
+impl<R0, R1, R2, Timer, SPI, Reg, OD> SharedArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    pub const fn new() -> Self {
+        SharedArray {
+            array: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Put `array` in, returning the one that was there before, if any.
+    pub fn install(
+        &self,
+        array: LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>,
+    ) -> Option<LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>> {
+        critical_section::with(|cs| self.array.borrow(cs).replace(Some(array)))
+    }
+
+    /// Run `f` on the array inside a critical section. Returns `None` without
+    /// running it if nothing is installed yet, or if called from inside
+    /// another `draw`.
+    pub fn draw<F, T>(&self, f: F) -> Option<T>
+    where
+        F: FnOnce(&mut LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>) -> T,
+    {
+        critical_section::with(|cs| {
+            let mut array = self.array.borrow(cs).try_borrow_mut().ok()?;
+            array.as_mut().map(f)
+        })
+    }
+
+    /// `LEDArray::step` inside a critical section, or `None` if nothing is
+    /// installed yet.
+    pub fn step<T, PinError>(
+        &self,
+        base_freq: T,
+    ) -> Option<Result<bool, LEDError<PinError, SPI::Error>>>
+    where
+        R0: OutputPin<Error = PinError>,
+        R1: OutputPin<Error = PinError>,
+        R2: OutputPin<Error = PinError>,
+        Timer: hal::timer::CountDown,
+        T: Into<Timer::Time> + Copy + core::ops::Shl<usize, Output = T>,
+        SPI: Write<u8>,
+        Reg: OutputPin<Error = PinError>,
+        OD: OutputPin<Error = PinError>,
+    {
+        self.draw(|array| array.step(base_freq))
+    }
+}
+
+impl<R0, R1, R2, Timer, SPI, Reg, OD> Default for SharedArray<R0, R1, R2, Timer, SPI, Reg, OD> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::tests::mock_array;
+    use crate::{LAYER_BITS, NUM_COLS, NUM_ROWS, SPI_BYTES};
+
+    #[test]
+    fn test_shared_array() {
+        let shared = SharedArray::new();
+        assert!(shared.draw(|array| array.frame_count).is_none());
+        assert!(shared.install(mock_array()).is_none());
+
+        // drawing can't reenter itself
+        assert_eq!(shared.draw(|_| shared.draw(|_| ())), Some(None));
+        assert!(shared.step(1).is_some_and(|result| result.is_ok()));
+    }
+
+    #[test]
+    fn test_shared_array_threads() {
+        let frames = 12;
+        let shared = SharedArray::new();
+        shared.install(mock_array());
+
+        // the "main loop" redraws each row a half at a time while the
+        // "interrupt" keeps stepping
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for i in 0..NUM_ROWS * LAYER_BITS * frames {
+                    shared.draw(|array| {
+                        let level = (i % 16) as u8;
+                        for row in array.array.iter_mut() {
+                            row[..NUM_COLS / 2].fill(level);
+                            std::thread::yield_now();
+                            row[NUM_COLS / 2..].fill(level);
+                        }
+                    });
+                }
+            });
+            scope.spawn(|| {
+                for _ in 0..NUM_ROWS * LAYER_BITS * frames {
+                    assert!(shared.step(1).is_some_and(|result| result.is_ok()));
+                    std::thread::yield_now();
+                }
+            });
+        });
+
+        // no row ever went out with its two halves from different draws
+        let written = shared.draw(|array| {
+            assert_eq!(array.frame_count, frames as u32);
+            array.spi.written.clone()
+        });
+        let written = written.unwrap_or_default();
+        assert_eq!(written.len(), NUM_ROWS * LAYER_BITS * SPI_BYTES * frames);
+        for layer in written.chunks(SPI_BYTES) {
+            assert!(layer.iter().all(|&byte| byte == 0 || byte == 0xff));
+            assert!(layer.iter().all(|&byte| byte == layer[0]));
+        }
+    }
+}
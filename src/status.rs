@@ -0,0 +1,93 @@
+use core::fmt;
+
+use crate::{LEDArray, Marquee};
+
+/// A line of text that keeps scrolling across the panel, and can be rewritten
+/// at any time, including with `write!`.
+pub struct StatusLine<const N: usize> {
+    text: heapless::String<N>,
+    pub brightness: u8,
+    position: usize,
+}
+
+impl<const N: usize> StatusLine<N> {
+    pub fn new(brightness: u8) -> Self {
+        StatusLine {
+            text: heapless::String::new(),
+            brightness,
+            position: 0,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replace the text and scroll back to its start. Fails without changing
+    /// anything if `text` is longer than `N` bytes.
+    pub fn set_text(&mut self, text: &str) -> fmt::Result {
+        if text.len() > N {
+            return Err(fmt::Error);
+        }
+        self.clear();
+        fmt::Write::write_str(self, text)
+    }
+
+    /// Empty the text and scroll back to the start.
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.position = 0;
+    }
+
+    /// Draw the current window of text over the whole array, then scroll one
+    /// column. Does nothing while the array is paused.
+    pub fn tick<R0, R1, R2, Timer, SPI, Reg, OD>(
+        &mut self,
+        array: &mut LEDArray<R0, R1, R2, Timer, SPI, Reg, OD>,
+    ) {
+        let mut marquee = Marquee {
+            text: &self.text,
+            brightness: self.brightness,
+            position: self.position,
+        };
+        marquee.render(array);
+        self.position = marquee.position;
+    }
+}
+
+/// Appends to the text, keeping the scroll position.
+impl<const N: usize> fmt::Write for StatusLine<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.text.push_str(s).map_err(|()| fmt::Error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::mock_array;
+    use core::fmt::Write;
+
+    #[test]
+    fn test_status_line() {
+        let mut array = mock_array();
+        let mut status = StatusLine::<8>::new(4);
+
+        status.set_text("I").unwrap();
+        status.tick(&mut array);
+        assert_eq!(array.array[3][..6], [0, 0, 4, 0, 0, 0]);
+        status.tick(&mut array);
+        assert_eq!(array.array[3][..6], [0, 4, 0, 0, 0, 0]);
+
+        status.clear();
+        write!(status, "{}", 1).unwrap();
+        assert_eq!(status.text(), "1");
+        status.tick(&mut array);
+        // the 1 starts over at the left, and the old I is gone
+        assert_eq!(array.array[6][..6], [0, 4, 4, 4, 0, 0]);
+        assert_eq!(array.array[3][..6], [0, 0, 4, 0, 0, 0]);
+
+        assert!(status.set_text("too long!").is_err());
+        assert_eq!(status.text(), "1");
+    }
+}
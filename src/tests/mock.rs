@@ -1,7 +1,5 @@
 #[cfg(test)]
 use embedded_hal as hal;
-use heapless;
-use heapless::consts::*;
 
 #[derive(Clone, Copy)]
 pub struct MockPin {
@@ -35,6 +33,51 @@ impl hal::digital::v2::OutputPin for MockPin {
     }
 }
 
+/// Every pin change made through `LogPin`s, in order.
+#[derive(Default)]
+pub struct EventLog {
+    events: core::cell::RefCell<heapless::Vec<(&'static str, bool), 256>>,
+}
+
+impl EventLog {
+    /// Return and clear the events logged so far.
+    pub fn take(&self) -> heapless::Vec<(&'static str, bool), 256> {
+        core::mem::take(&mut *self.events.borrow_mut())
+    }
+}
+
+/// A pin that records each change to a shared `EventLog`.
+pub struct LogPin<'a> {
+    pub name: &'static str,
+    pub log: &'a EventLog,
+}
+
+impl<'a> LogPin<'a> {
+    pub fn new(name: &'static str, log: &'a EventLog) -> LogPin<'a> {
+        LogPin { name, log }
+    }
+}
+
+impl<'a> hal::digital::v2::OutputPin for LogPin<'a> {
+    type Error = ();
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.log
+            .events
+            .borrow_mut()
+            .push((self.name, false))
+            .map_err(|_| ())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.log
+            .events
+            .borrow_mut()
+            .push((self.name, true))
+            .map_err(|_| ())
+    }
+}
+
 pub struct MockTimer {
     pub tries: i32,
 }
@@ -59,23 +102,231 @@ impl hal::timer::CountDown for MockTimer {
     }
 }
 
+/// A timer that counts in `u32` and keeps every duration it was started
+/// with. It always expires straight away.
+pub struct RecordingTimer {
+    pub started: heapless::Vec<u32, 64>,
+}
+
+impl RecordingTimer {
+    pub fn new() -> RecordingTimer {
+        RecordingTimer {
+            started: heapless::Vec::new(),
+        }
+    }
+}
+
+impl hal::timer::CountDown for RecordingTimer {
+    type Time = u32;
+
+    fn start<T>(&mut self, duration: T)
+    where
+        T: Into<Self::Time>,
+    {
+        // the tests never start it more than 64 times between checks
+        let _ = self.started.push(duration.into());
+    }
+
+    fn wait(&mut self) -> Result<(), nb::Error<void::Void>> {
+        Ok(())
+    }
+}
+
 pub struct MockSPI {
-    pub written: heapless::Vec<u8, U64>,
+    pub written: heapless::Vec<u8, 2048>,
+    /// How many separate blocking writes were made.
+    pub transfers: usize,
+    /// Read back what falls out the end of a `SPI_BYTES` long shift register
+    /// chain, instead of failing every read.
+    pub loopback: bool,
+    /// Bits that always read back low, by position in the chain, to fake
+    /// dead registers.
+    pub stuck_low: [u8; crate::SPI_BYTES],
+    /// How many more `FullDuplex` calls report `WouldBlock` before the bus
+    /// goes through.
+    pub busy: u32,
+}
+
+impl MockSPI {
+    pub fn new() -> MockSPI {
+        MockSPI {
+            written: heapless::Vec::new(),
+            transfers: 0,
+            loopback: false,
+            stuck_low: [0; crate::SPI_BYTES],
+            busy: 0,
+        }
+    }
 }
 
 impl hal::spi::FullDuplex<u8> for MockSPI {
     type Error = ();
 
     fn read(&mut self) -> Result<u8, nb::Error<Self::Error>> {
-        // crash if we try to read
-        Err(nb::Error::Other(()))
+        if self.busy > 0 {
+            self.busy -= 1;
+            return Err(nb::Error::WouldBlock);
+        }
+        if !self.loopback {
+            // crash if we try to read
+            return Err(nb::Error::Other(()));
+        }
+
+        // the byte pushed out by the last one sent, or 0 while the chain is
+        // still filling
+        let index = match self.written.len().checked_sub(crate::SPI_BYTES + 1) {
+            Some(index) => index,
+            None => return Ok(0),
+        };
+        Ok(self.written[index] & !self.stuck_low[index % crate::SPI_BYTES])
     }
 
     fn send(&mut self, word: u8) -> Result<(), nb::Error<Self::Error>> {
+        if self.busy > 0 {
+            self.busy -= 1;
+            return Err(nb::Error::WouldBlock);
+        }
         self.written.push(word).map_err(|_| nb::Error::Other(()))
     }
 }
 
+impl hal::blocking::spi::Write<u8> for MockSPI {
+    type Error = ();
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.transfers += 1;
+        self.written.extend_from_slice(words)
+    }
+}
+
+/// An SPI bus that logs each write to an `EventLog` as `("spi", true)`, so
+/// writes can be ordered against pin changes.
+pub struct LogSPI<'a> {
+    pub written: heapless::Vec<u8, 512>,
+    pub log: &'a EventLog,
+}
+
+impl<'a> LogSPI<'a> {
+    pub fn new(log: &'a EventLog) -> LogSPI<'a> {
+        LogSPI {
+            written: heapless::Vec::new(),
+            log,
+        }
+    }
+}
+
+impl<'a> hal::blocking::spi::Write<u8> for LogSPI<'a> {
+    type Error = ();
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.log
+            .events
+            .borrow_mut()
+            .push(("spi", true))
+            .map_err(|_| ())?;
+        self.written.extend_from_slice(words)
+    }
+}
+
+/// An SPI bus with no read side at all.
+pub struct MockWriteSPI {
+    pub written: heapless::Vec<u8, 512>,
+}
+
+impl hal::blocking::spi::Write<u8> for MockWriteSPI {
+    type Error = ();
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.written.extend_from_slice(words)
+    }
+}
+
+/// A timer that reports how many ticks it has left, which the test counts
+/// down by hand.
+pub struct CountingTimer {
+    pub left: u32,
+}
+
+impl hal::timer::CountDown for CountingTimer {
+    type Time = u32;
+
+    fn start<T>(&mut self, duration: T)
+    where
+        T: Into<Self::Time>,
+    {
+        self.left = duration.into();
+    }
+
+    fn wait(&mut self) -> Result<(), nb::Error<void::Void>> {
+        match self.left {
+            0 => Ok(()),
+            _ => Err(nb::Error::WouldBlock),
+        }
+    }
+}
+
+impl crate::Remaining for CountingTimer {
+    fn remaining(&self) -> u32 {
+        self.left
+    }
+}
+
+/// A PWM channel that just remembers its settings.
+pub struct MockPwm {
+    pub duty: u16,
+    pub max_duty: u16,
+    pub enabled: bool,
+}
+
+impl hal::PwmPin for MockPwm {
+    type Duty = u16;
+
+    fn disable(&mut self) {
+        self.enabled = false;
+    }
+
+    fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    fn get_duty(&self) -> u16 {
+        self.duty
+    }
+
+    fn get_max_duty(&self) -> u16 {
+        self.max_duty
+    }
+
+    fn set_duty(&mut self, duty: u16) {
+        self.duty = duty;
+    }
+}
+
+/// A defmt logger that only counts the bytes logged, so tests can check that
+/// something was formatted without decoding it.
+#[cfg(feature = "defmt")]
+#[defmt::global_logger]
+pub struct CountingLogger;
+
+#[cfg(feature = "defmt")]
+pub static LOGGED_BYTES: core::sync::atomic::AtomicUsize = core::sync::atomic::AtomicUsize::new(0);
+
+#[cfg(feature = "defmt")]
+unsafe impl defmt::Logger for CountingLogger {
+    fn acquire() {}
+
+    unsafe fn flush() {}
+
+    unsafe fn release() {}
+
+    unsafe fn write(bytes: &[u8]) {
+        LOGGED_BYTES.fetch_add(bytes.len(), core::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+#[cfg(feature = "defmt")]
+defmt::timestamp!("");
+
 mod test {
     use super::*;
     use hal::digital::v2::OutputPin;
@@ -87,11 +338,11 @@ mod test {
         let mut pin = MockPin::new();
 
         pin.set_low().unwrap();
-        assert_eq!(pin.state, false);
+        assert!(!pin.state);
         assert_eq!(pin.cycles, 0);
 
         pin.set_high().unwrap();
-        assert_eq!(pin.state, true);
+        assert!(pin.state);
         assert_eq!(pin.cycles, 0);
 
         pin.set_low().unwrap();
@@ -111,9 +362,7 @@ mod test {
 
     #[test]
     fn test_mock_spi() {
-        let mut bus = MockSPI {
-            written: heapless::Vec::new(),
-        };
+        let mut bus = MockSPI::new();
 
         bus.send(0u8).unwrap();
         bus.send(157u8).unwrap();
@@ -121,4 +370,19 @@ mod test {
 
         assert_eq!(bus.read(), Err(nb::Error::Other(())));
     }
+
+    #[test]
+    fn test_mock_spi_loopback() {
+        let mut bus = MockSPI::new();
+        bus.loopback = true;
+        bus.stuck_low = [0x01, 0x00];
+
+        for &word in [0x11u8, 0x22, 0x33, 0x44].iter() {
+            bus.send(word).unwrap();
+        }
+        assert_eq!(bus.read(), Ok(0x22));
+
+        bus.send(0).unwrap();
+        assert_eq!(bus.read(), Ok(0x32));
+    }
 }
@@ -0,0 +1,111 @@
+use core::convert::TryFrom;
+
+use crate::{NUM_COLS, NUM_ROWS};
+
+/// How far a panel is turned clockwise from the way it is wired.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Rotation {
+    #[default]
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+/// How drawing coordinates map onto the panel's rows and columns, for panels
+/// mounted turned or flipped, or wired back and forth.
+///
+/// The steps always apply in the same order: mirror in drawing coordinates,
+/// then rotate, then undo the serpentine wiring. Turned a quarter, the
+/// drawing area is `NUM_ROWS` wide and `NUM_COLS` tall.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Transform {
+    pub rotation: Rotation,
+    /// Flip left to right.
+    pub mirror_x: bool,
+    /// Flip top to bottom.
+    pub mirror_y: bool,
+    /// Every odd row is wired right to left.
+    pub serpentine: bool,
+}
+
+impl Transform {
+    /// The width and height of the drawing area.
+    pub fn size(&self) -> (usize, usize) {
+        match self.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => (NUM_COLS, NUM_ROWS),
+            Rotation::Deg90 | Rotation::Deg270 => (NUM_ROWS, NUM_COLS),
+        }
+    }
+
+    /// The `(row, col)` in `array` that drawing coordinate `(x, y)` lands on,
+    /// or `None` if it is off the drawing area.
+    pub fn apply(&self, x: i32, y: i32) -> Option<(usize, usize)> {
+        let (width, height) = self.size();
+        let x = usize::try_from(x).ok().filter(|&x| x < width)?;
+        let y = usize::try_from(y).ok().filter(|&y| y < height)?;
+
+        let x = if self.mirror_x { width - 1 - x } else { x };
+        let y = if self.mirror_y { height - 1 - y } else { y };
+
+        let (row, col) = match self.rotation {
+            Rotation::Deg0 => (y, x),
+            Rotation::Deg90 => (x, NUM_COLS - 1 - y),
+            Rotation::Deg180 => (NUM_ROWS - 1 - y, NUM_COLS - 1 - x),
+            Rotation::Deg270 => (NUM_ROWS - 1 - x, y),
+        };
+
+        let col = if self.serpentine && row % 2 == 1 {
+            NUM_COLS - 1 - col
+        } else {
+            col
+        };
+        Some((row, col))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity() {
+        let transform = Transform::default();
+        assert_eq!(transform.apply(3, 2), Some((2, 3)));
+        assert_eq!(transform.apply(NUM_COLS as i32, 0), None);
+        assert_eq!(transform.apply(0, -1), None);
+    }
+
+    #[test]
+    fn test_rotate_90_mirror_x() {
+        let rotated = Transform {
+            rotation: Rotation::Deg90,
+            ..Transform::default()
+        };
+        // the top left corner turns to the top right
+        assert_eq!(rotated.apply(0, 0), Some((0, 15)));
+        assert_eq!(rotated.apply(7, 15), Some((7, 0)));
+        assert_eq!(rotated.apply(8, 0), None);
+
+        let both = Transform {
+            mirror_x: true,
+            ..rotated
+        };
+        // flipped first, so the top left comes from the top right
+        assert_eq!(both.apply(0, 0), Some((7, 15)));
+        assert_eq!(both.apply(7, 0), Some((0, 15)));
+        assert_eq!(both.apply(2, 5), Some((5, 10)));
+    }
+
+    #[test]
+    fn test_serpentine() {
+        let transform = Transform {
+            rotation: Rotation::Deg180,
+            serpentine: true,
+            ..Transform::default()
+        };
+        // rotated onto row 7, which runs backwards
+        assert_eq!(transform.apply(0, 0), Some((7, 0)));
+        assert_eq!(transform.apply(0, 1), Some((6, 15)));
+    }
+}